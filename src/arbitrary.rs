@@ -0,0 +1,72 @@
+use alloc::string::String;
+
+use crate::{mix, Alignment, BreakAlgorithm, GapStrategy, LastLineMode, NewlinePolicy, Options, OverflowPolicy};
+
+const WORDS: &[&str] = &[
+    "a", "Lorem", "ipsum", "dolor", "sit", "amet,", "consectetur", "adipiscing", "elit.", "state-of-the-art",
+    "un\u{AD}der\u{AD}stand\u{AD}ing", "https://example.com/path", "5", "kg", "Dr.", "\u{FEFF}", "«", "»", "—", "x\u{0301}",
+];
+
+#[derive(Clone, Debug)]
+pub struct Generator {                                                               // Воспроизводимые входные данные для проверки свойств кода, использующего библиотеку
+    state: u64,
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Generator {
+        Generator { state: mix(seed) }
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = mix(self.state);
+        self.state
+    }
+    pub fn below(&mut self, bound: usize) -> usize {                                // От 0 до bound не включая; 0 при bound = 0
+        if bound == 0 { 0 } else { (self.next_u64() % bound as u64) as usize }
+    }
+    pub fn chance(&mut self, numerator: usize, denominator: usize) -> bool {
+        self.below(denominator) < numerator
+    }
+    fn pick<T: Copy>(&mut self, values: &[T]) -> T {
+        values[self.below(values.len())]
+    }
+    pub fn line_width(&mut self) -> usize {                                         // От 1 до 80, чаще узкие
+        let widest: usize = 1 + self.below(80);
+        1 + self.below(widest)
+    }
+    pub fn text(&mut self, max_words: usize) -> String {                           // Слова с пробелами, переводами строк и пустыми строками между абзацами
+        let mut text: String = String::new();
+        for word in 0..self.below(max_words + 1) {
+            if word > 0 {
+                text.push_str(self.pick(&[" ", " ", " ", "  ", "\n", "\n\n", "\t"]));
+            }
+            text.push_str(self.pick(WORDS));
+        }
+        text
+    }
+    pub fn options(&mut self) -> Options {                                          // Обычные параметры выравнивания и разбиения; measure, segmenter, splitters и tokenizer - по умолчанию
+        Options {
+            alignment: self.pick(&[Alignment::Left, Alignment::Right, Alignment::Center, Alignment::Justify]),
+            last_line: self.pick(&[LastLineMode::Justify, LastLineMode::Left, LastLineMode::Center, LastLineMode::Right]),
+            break_algorithm: self.pick(&[BreakAlgorithm::Greedy, BreakAlgorithm::Optimal, BreakAlgorithm::Balanced]),
+            gap_strategy: match self.below(5) {
+                0 => GapStrategy::LeftBiased,
+                1 => GapStrategy::RightBiased,
+                2 => GapStrategy::CenterOut,
+                3 => GapStrategy::Alternating,
+                _ => GapStrategy::SeededRandom(self.next_u64()),
+            },
+            overflow: self.pick(&[OverflowPolicy::Error, OverflowPolicy::BreakAnywhere, OverflowPolicy::Overflow, OverflowPolicy::Truncate]),
+            newlines: self.pick(&[NewlinePolicy::Collapse, NewlinePolicy::Preserve, NewlinePolicy::ParagraphOnly]),
+            avoid_rivers: self.chance(1, 4),
+            avoid_widows: self.chance(1, 4),
+            break_at_hyphens: self.chance(1, 4),
+            keep_urls: self.chance(1, 4),
+            sentence_spacing: self.chance(1, 4),
+            hanging_punctuation: self.chance(1, 4),
+            pad_short_lines: self.chance(3, 4),
+            min_gap: 1 + self.below(2),
+            max_lines: self.chance(1, 8).then(|| 1 + self.below(5)),
+            ..Options::default()
+        }
+    }
+}
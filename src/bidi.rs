@@ -0,0 +1,108 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::width::Graphemes;
+use crate::Direction;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Left,
+    Right,
+    Number,
+    Neutral,
+}
+
+const RIGHT_TO_LEFT: &[(u32, u32)] = &[                                             // Иврит, арабское, сирийское и другие письма справа налево
+    (0x0590, 0x08FF), (0x200F, 0x200F), (0xFB1D, 0xFDFF), (0xFE70, 0xFEFF), (0x10800, 0x10FFF),
+    (0x1E800, 0x1EFFF),
+];
+
+const MIRRORED: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}'), ('<', '>'), ('«', '»'), ('‹', '›')];
+
+fn class(c: char) -> Option<Class> {
+    let code: u32 = c as u32;
+    if c.is_ascii_digit() || (0x0660..=0x0669).contains(&code) || (0x06F0..=0x06F9).contains(&code) {
+        Some(Class::Number)
+    } else if RIGHT_TO_LEFT.iter().any(|&(start, end)| (start..=end).contains(&code)) {
+        (c.is_alphabetic() || code == 0x200F).then_some(Class::Right)
+    } else {
+        c.is_alphabetic().then_some(Class::Left)
+    }
+}
+
+fn word_class(word: &str) -> Class {                                                // Направление слова - по первому сильному символу или цифре
+    word.chars().find_map(class).unwrap_or(Class::Neutral)
+}
+
+pub(crate) fn levels(words: &[&str], base: Direction) -> Vec<u8> {                  // Уровни вложенности слов строки по упрощённому UAX #9
+    let base: u8 = if base == Direction::Rtl { 1 } else { 0 };
+    let classes: Vec<Class> = words.iter().map(|word| word_class(word)).collect();
+    let strong = |class: Class, previous: Class| -> Class {                          // Числа считаются частью окружающего их письма справа налево
+        if class == Class::Number && (base == 1 || previous == Class::Right) { Class::Right } else { class }
+    };
+    let mut resolved: Vec<Class> = Vec::with_capacity(classes.len());
+    let mut previous: Class = if base == 1 { Class::Right } else { Class::Left };
+    for &class in &classes {
+        let class: Class = strong(class, previous);
+        if class == Class::Left || class == Class::Right {
+            previous = class;
+        }
+        resolved.push(class);
+    }
+    let direction = |class: Class| -> Option<bool> {
+        match class {
+            Class::Right => Some(true),
+            Class::Left | Class::Number => Some(false),
+            Class::Neutral => None,
+        }
+    };
+    (0..resolved.len())
+        .map(|index| {
+            let rtl: bool =
+                match direction(resolved[index]) {
+                    Some(rtl) => rtl,
+                    None => {                                                       // Нейтральное слово между словами одного направления принимает его
+                        let before: Option<bool> = resolved[..index].iter().rev().find_map(|&class| direction(class));
+                        let after: Option<bool> = resolved[index + 1..].iter().find_map(|&class| direction(class));
+                        match (before, after) {
+                            (Some(before), Some(after)) if before == after => before,
+                            _ => base == 1,
+                        }
+                    }
+                };
+            let number: bool = classes[index] == Class::Number;
+            match (base, rtl) {
+                (_, true) if number => 2,                                           // Цифры внутри письма справа налево пишутся слева направо
+                (0, false) => 0,
+                (_, true) => 1,
+                _ => 2,
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn visual_order(levels: &[u8]) -> Vec<usize> {                           // Порядок вывода слов: отрезки с уровнем не ниже каждого нечётного переворачиваются
+    let mut order: Vec<usize> = (0..levels.len()).collect();
+    let lowest_odd: u8 = levels.iter().copied().filter(|level| level % 2 == 1).min().unwrap_or(1);
+    for level in (lowest_odd..=levels.iter().copied().max().unwrap_or(0)).rev() {
+        let mut start: usize = 0;
+        while start < order.len() {
+            if levels[order[start]] < level {
+                start += 1;
+                continue;
+            }
+            let end: usize = (start..order.len()).find(|&index| levels[order[index]] < level).unwrap_or(order.len());
+            order[start..end].reverse();
+            start = end;
+        }
+    }
+    order
+}
+
+pub(crate) fn reversed(word: &str) -> String {                                      // Слово справа налево в порядке вывода: кластеры графем задом наперёд, скобки отражены
+    let clusters: Vec<&str> = Graphemes::new(word).collect();
+    clusters.iter().rev()
+        .flat_map(|cluster| cluster.chars())
+        .map(|c| MIRRORED.iter().find_map(|&(open, close)| if c == open { Some(close) } else if c == close { Some(open) } else { None }).unwrap_or(c))
+        .collect()
+}
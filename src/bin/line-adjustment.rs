@@ -0,0 +1,92 @@
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use std::process::ExitCode;
+
+use line_adjustment::{transform_reader, Options, StreamError};
+
+const USAGE: &str = "usage: line-adjustment [--width N] [FILE]...\n\nJustifies text from the files (or standard input, also `-`) to the given width (80 by default).";
+
+struct Args {
+    line_width: usize,
+    files: Vec<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args: Args = Args { line_width: 80, files: Vec::new() };
+    let mut input = std::env::args().skip(1);
+    while let Some(arg) = input.next() {
+        let width: Option<String> = match arg.as_str() {
+            "-h" | "--help" => return Err(String::new()),
+            "-w" | "--width" => Some(input.next().ok_or("--width expects a value")?),
+            _ => arg.strip_prefix("--width=").map(str::to_string),
+        };
+        match width {
+            Some(width) => args.line_width = width.parse().map_err(|_| format!("invalid width {:?}", width))?,
+            None => args.files.push(arg),
+        }
+    }
+    if args.files.is_empty() {
+        args.files.push(String::from("-"));                                         // Без файлов читаем стандартный ввод
+    }
+    Ok(args)
+}
+
+struct Separated<W: Write> {                                                        // Вывод файлов через пустую строку: пустые файлы её не добавляют
+    inner: W,
+    started: bool,                              // Что-то уже выведено
+    separate: bool,                             // Перед следующим выводом нужна пустая строка
+}
+
+impl<W: Write> Write for Separated<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.separate {
+            self.inner.write_all(b"\n")?;
+            self.separate = false;
+        }
+        self.started = true;
+        self.inner.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn justify<W: Write>(file: &str, writer: &mut W, line_width: usize) -> Result<(), StreamError> {
+    let options: Options = Options { ensure_trailing_newline: true, ..Options::default() };
+    if file == "-" {
+        transform_reader(io::stdin().lock(), writer, line_width, options)
+    } else {
+        transform_reader(BufReader::new(File::open(file)?), writer, line_width, options)
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Args = match parse_args() {
+        Ok(args) => args,
+        Err(message) if message.is_empty() => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("line-adjustment: {}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut stdout: Separated<io::BufWriter<io::StdoutLock>> = Separated { inner: io::BufWriter::new(io::stdout().lock()), started: false, separate: false };
+    for file in &args.files {
+        stdout.separate = stdout.started;                                           // Файлы разделяются пустой строкой, как абзацы
+        if let Err(error) = justify(file, &mut stdout, args.line_width) {
+            let _ = stdout.flush();
+            eprintln!("line-adjustment: {}: {}", file, error);
+            return ExitCode::FAILURE;
+        }
+    }
+    match stdout.flush() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(_) => ExitCode::FAILURE,
+    }
+}
@@ -0,0 +1,31 @@
+use alloc::vec::Vec;
+
+use crate::width::Graphemes;
+
+const NO_LINE_START: &str =                                                          // Кинсоку: эти знаки не начинают строку
+    "、。，．・：；？！‐ー）」』】〕〉》〙〗｝］ヽヾゝゞ々ぁぃぅぇぉっゃゅょゎゕゖァィゥェォッャュョヮヵヶ,.!?;:)]}’”%";
+
+const NO_LINE_END: &str = "（「『【〔〈《〘〖｛［([{‘“";                                 // А эти не заканчивают её
+
+fn is_cjk(c: char) -> bool {                                                        // Иероглифы, кана, знаки препинания и полноширинные формы
+    matches!(c,
+        '\u{3000}'..='\u{303F}' | '\u{3040}'..='\u{30FF}' | '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}' | '\u{FF00}'..='\u{FFEF}' | '\u{20000}'..='\u{2FFFD}' | '\u{30000}'..='\u{3FFFD}')
+}
+
+pub(crate) fn break_points(text: &str) -> Vec<usize> {                             // Байты внутри слова, где можно перенести строку без знака переноса
+    let mut points: Vec<usize> = Vec::new();
+    let mut offset: usize = 0;
+    let mut previous: Option<char> = None;
+    for cluster in Graphemes::new(text) {
+        let first: char = cluster.chars().next().unwrap_or(' ');
+        if let Some(previous) = previous {
+            if (is_cjk(previous) || is_cjk(first)) && !NO_LINE_START.contains(first) && !NO_LINE_END.contains(previous) {
+                points.push(offset);
+            }
+        }
+        previous = Some(first);
+        offset += cluster.len();
+    }
+    points
+}
@@ -0,0 +1,119 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::{paragraph_ranges, separator_prefix, tokenize, Continuation, DocError, Document, GapStrategy, Options};
+
+struct Paragraph {
+    range: Range<usize>,                        // Байты абзаца в тексте редактора
+    line_count: usize,                          // Число выходных строк абзаца вместе с разделителем перед ним
+    first_line: usize,                          // Строк текста выше абзаца - для Options::max_lines и счёта строк GapStrategy
+}
+
+pub struct Editor {                                                                 // Владеет текстом и после правок переразбивает только затронутые абзацы
+    text: String,
+    line_width: usize,
+    options: Options,
+    paragraphs: Vec<Paragraph>,
+    lines: Vec<String>,
+}
+
+impl Editor {
+    pub fn new<S: Into<String>>(text: S, line_width: usize, options: Options) -> Result<Editor, DocError> {
+        let mut editor: Editor = Editor {
+            text: text.into(),
+            line_width,
+            options,
+            paragraphs: Vec::new(),
+            lines: Vec::new(),
+        };
+        editor.reflow(0..0, 0)?;
+        Ok(editor)
+    }
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    pub fn lines(&self) -> &[String] {                                              // Те же строки, что и у Document::format_lines для всего текста редактора
+        &self.lines
+    }
+    pub fn insert(&mut self, offset: usize, text: &str) -> Result<Range<usize>, DocError> {
+        self.replace(offset..offset, text)
+    }
+    pub fn delete(&mut self, range: Range<usize>) -> Result<Range<usize>, DocError> {
+        self.replace(range, "")
+    }
+    pub fn replace(&mut self, range: Range<usize>, text: &str) -> Result<Range<usize>, DocError> {  // Возвращает номера изменившихся строк; строки после них лишь сдвигаются
+        let removed: String = self.text[range.clone()].to_string();
+        self.text.replace_range(range.clone(), text);
+        self.reflow(range.clone(), text.len()).inspect_err(|_| {
+            self.text.replace_range(range.start..range.start + text.len(), &removed);   // При ошибке редактор остаётся в прежнем состоянии
+        })
+    }
+    fn paragraph_lines(&self, range: Range<usize>, index: usize, shown: usize, more: bool) -> Result<(Vec<String>, usize), DocError> {   // shown - строк текста выше абзаца, more - есть ли абзацы ниже
+        let options: Options = Options { max_lines: self.options.max_lines.map(|max_lines| max_lines.saturating_sub(shown)), ..self.options.clone() };
+        let mut document: Document =
+            Document::from_str_with(&self.text[range.clone()], self.line_width, options)
+                .map_err(|error| error.shifted(range.start, tokenize(&self.text[..range.start], &self.options).0.len()))?;
+        document.continuation = Continuation { first_line: shown, more };
+        let text_lines: usize = document.shown_lines().len();
+        let lines: Vec<String> = document.into_lines();
+        if index == 0 || lines.is_empty() {                                         // Абзац за исчерпанным Options::max_lines не выводится вовсе
+            return Ok((lines, text_lines));
+        }
+
+        let mut pieces: Vec<&str> = self.options.paragraph_separator.split('\n').collect();
+        let prefix: String = pieces.pop().unwrap_or_default().to_string();          // Хвост разделителя без перевода строки начинает первую строку абзаца
+        let mut output: Vec<String> = pieces.into_iter().map(|piece| [separator_prefix(piece, &self.options), piece].concat()).collect();
+        let mut lines = lines.into_iter();
+        output.extend(lines.next().map(|line| prefix + &line));
+        output.extend(lines);
+        Ok((output, text_lines))
+    }
+    fn reflow(&mut self, edit: Range<usize>, inserted: usize) -> Result<Range<usize>, DocError> {
+        let shift = |offset: usize| offset + inserted - edit.len();                 // Старое смещение после правки (только для смещений за ней)
+        let first: usize = match self.options.max_lines {
+            Some(_) => 0,                                                           // Остаток Options::max_lines и многоточие зависят от всего текста: переразбиваем его весь
+            None => self.paragraphs.partition_point(|paragraph| paragraph.range.start <= edit.start).saturating_sub(1),
+        };
+        let start: usize = match self.paragraphs.get(first) {                       // Абзацы до правки не меняются: начинаем с абзаца, в который она попала
+            Some(paragraph) if paragraph.range.start <= edit.start => paragraph.range.start,
+            _ => 0,
+        };
+
+        let mut paragraphs: Vec<Paragraph> = Vec::new();
+        let mut lines: Vec<String> = Vec::new();
+        let mut resync: Option<usize> = None;
+        let mut shown: usize = self.paragraphs.get(first).map_or(0, |paragraph| paragraph.first_line);
+        let numbered: bool = self.options.avoid_rivers || matches!(self.options.gap_strategy, GapStrategy::Alternating | GapStrategy::SeededRandom(_));   // Промежутки зависят от номера строки во всём тексте
+        let mut ranges = paragraph_ranges(&self.text[start..], &self.options).into_iter().peekable();
+        while let Some(range) = ranges.next() {
+            let range: Range<usize> = range.start + start..range.end + start;
+            let index: usize = first + paragraphs.len();
+            if range.start >= edit.start + inserted && self.options.max_lines.is_none() {                              // Дальше текст не менялся: ищем абзац, совпадающий со старым
+                let old: usize = self.paragraphs.partition_point(|paragraph| paragraph.range.start < edit.end || shift(paragraph.range.start) < range.start);
+                if self.paragraphs.get(old).is_some_and(|paragraph| shift(paragraph.range.start) == range.start && shift(paragraph.range.end) == range.end && (old == 0) == (index == 0) && (!numbered || paragraph.first_line == shown)) {
+                    resync = Some(old);
+                    break;
+                }
+            }
+            let (paragraph_lines, text_lines): (Vec<String>, usize) = self.paragraph_lines(range.clone(), index, shown, ranges.peek().is_some())?;
+            paragraphs.push(Paragraph { range, line_count: paragraph_lines.len(), first_line: shown });
+            shown += text_lines;
+            lines.extend(paragraph_lines);
+        }
+
+        let first_line: usize = self.paragraphs[..first].iter().map(|paragraph| paragraph.line_count).sum();
+        let resync: usize = resync.unwrap_or(self.paragraphs.len());
+        let old_lines: usize = self.paragraphs[first..resync].iter().map(|paragraph| paragraph.line_count).sum();
+        let changed: Range<usize> = first_line..first_line + lines.len();
+
+        let moved: usize = self.paragraphs.get(resync).map_or(shown, |paragraph| paragraph.first_line);   // Прежний номер первой строки текста за правкой
+        for paragraph in &mut self.paragraphs[resync..] {
+            paragraph.range = shift(paragraph.range.start)..shift(paragraph.range.end);
+            paragraph.first_line = paragraph.first_line + shown - moved;
+        }
+        self.paragraphs.splice(first..resync, paragraphs);
+        self.lines.splice(first_line..first_line + old_lines, lines);
+        Ok(changed)
+    }
+}
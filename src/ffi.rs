@@ -0,0 +1,176 @@
+// C API с непрозрачными указателями. Для C/C++ библиотеку собирают так:
+// cargo rustc --release --features ffi --lib --crate-type cdylib
+//
+// typedef struct LaDocument LaDocument;
+// typedef struct LaResult LaResult;
+// typedef struct LaOptions { uint32_t alignment; uint32_t last_line; uint32_t algorithm; } LaOptions;
+// enum { LA_ALIGN_LEFT, LA_ALIGN_RIGHT, LA_ALIGN_CENTER, LA_ALIGN_JUSTIFY };
+// enum { LA_LAST_LINE_JUSTIFY, LA_LAST_LINE_LEFT, LA_LAST_LINE_CENTER, LA_LAST_LINE_RIGHT };
+// enum { LA_ALGORITHM_GREEDY, LA_ALGORITHM_OPTIMAL, LA_ALGORITHM_BALANCED };
+// LaOptions la_options_default(void);
+// LaDocument *la_document_new(const char *input, size_t line_width);
+// LaDocument *la_document_new_with(const char *input, size_t line_width, const LaOptions *options);
+// void la_document_free(LaDocument *document);
+// LaResult *la_document_format(const LaDocument *document);
+// bool la_result_ok(const LaResult *result);
+// const char *la_result_text(const LaResult *result);
+// void la_result_free(LaResult *result);
+
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+use crate::{Alignment, BreakAlgorithm, DocError, DocumentOwned, LastLineMode, Options};
+
+pub const LA_ALIGN_LEFT: u32 = 0;
+pub const LA_ALIGN_RIGHT: u32 = 1;
+pub const LA_ALIGN_CENTER: u32 = 2;
+pub const LA_ALIGN_JUSTIFY: u32 = 3;
+pub const LA_LAST_LINE_JUSTIFY: u32 = 0;
+pub const LA_LAST_LINE_LEFT: u32 = 1;
+pub const LA_LAST_LINE_CENTER: u32 = 2;
+pub const LA_LAST_LINE_RIGHT: u32 = 3;
+pub const LA_ALGORITHM_GREEDY: u32 = 0;
+pub const LA_ALGORITHM_OPTIMAL: u32 = 1;
+pub const LA_ALGORITHM_BALANCED: u32 = 2;
+
+const ALIGNMENTS: [Alignment; 4] = [Alignment::Left, Alignment::Right, Alignment::Center, Alignment::Justify];   // По номерам LA_ALIGN_*
+const LAST_LINES: [LastLineMode; 4] = [LastLineMode::Justify, LastLineMode::Left, LastLineMode::Center, LastLineMode::Right];
+const ALGORITHMS: [BreakAlgorithm; 3] = [BreakAlgorithm::Greedy, BreakAlgorithm::Optimal, BreakAlgorithm::Balanced];
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LaOptions {                                                              // Основные поля Options числами: C-перечисление с чужим значением не сломает Rust
+    pub alignment: u32,                         // LA_ALIGN_*
+    pub last_line: u32,                         // LA_LAST_LINE_*
+    pub algorithm: u32,                         // LA_ALGORITHM_*
+}
+
+impl LaOptions {
+    fn options(&self) -> Option<Options> {                                         // None для неизвестного номера
+        let code = |code: u32| usize::try_from(code).ok();
+        Some(Options {
+            alignment: *ALIGNMENTS.get(code(self.alignment)?)?,
+            last_line: *LAST_LINES.get(code(self.last_line)?)?,
+            break_algorithm: *ALGORITHMS.get(code(self.algorithm)?)?,
+            ..Options::default()
+        })
+    }
+}
+
+pub struct LaDocument {
+    document: Result<DocumentOwned, DocError>,  // Разбит на строки один раз при создании
+}
+
+pub struct LaResult {
+    ok: bool,
+    text: CString,                              // Отформатированный текст или сообщение об ошибке
+}
+
+fn c_string(text: String) -> CString {
+    CString::new(text).unwrap_or_else(|error| {                                     // Нулевой байт из входного текста в C-строке не выразить
+        let end: usize = error.nul_position();
+        let mut bytes: Vec<u8> = error.into_vec();
+        bytes.truncate(end);
+        CString::new(bytes).unwrap_or_default()
+    })
+}
+
+/// Настройки по умолчанию, как у `Options::default()`.
+#[no_mangle]
+pub extern "C" fn la_options_default() -> LaOptions {
+    let options: Options = Options::default();
+    let code = |index: Option<usize>| index.and_then(|index| u32::try_from(index).ok()).unwrap_or_default();
+    LaOptions {
+        alignment: code(ALIGNMENTS.iter().position(|&alignment| alignment == options.alignment)),
+        last_line: code(LAST_LINES.iter().position(|&last_line| last_line == options.last_line)),
+        algorithm: code(ALGORITHMS.iter().position(|&algorithm| algorithm == options.break_algorithm)),
+    }
+}
+
+/// Документ из UTF-8 строки, завершённой нулём, с настройками по умолчанию. NULL, если строка NULL или не UTF-8.
+///
+/// # Safety
+///
+/// `input` - NULL или указатель на строку, завершённую нулём.
+#[no_mangle]
+pub unsafe extern "C" fn la_document_new(input: *const c_char, line_width: usize) -> *mut LaDocument {
+    let options: LaOptions = la_options_default();
+    la_document_new_with(input, line_width, &options)
+}
+
+/// Документ с настройками `options`. NULL, если строка NULL или не UTF-8, `options` NULL или в нём неизвестный номер.
+///
+/// # Safety
+///
+/// `input` - NULL или указатель на строку, завершённую нулём; `options` - NULL или действующий указатель.
+#[no_mangle]
+pub unsafe extern "C" fn la_document_new_with(input: *const c_char, line_width: usize, options: *const LaOptions) -> *mut LaDocument {
+    if input.is_null() {
+        return ptr::null_mut();
+    }
+    let (Ok(input), Some(options)) = (CStr::from_ptr(input).to_str(), options.as_ref().and_then(LaOptions::options)) else {
+        return ptr::null_mut();
+    };
+    Box::into_raw(Box::new(LaDocument { document: DocumentOwned::from_str_with(input, line_width, options) }))
+}
+
+/// Освобождает документ из `la_document_new` или `la_document_new_with`.
+///
+/// # Safety
+///
+/// `document` - NULL или ещё не освобождённый указатель из `la_document_new` или `la_document_new_with`.
+#[no_mangle]
+pub unsafe extern "C" fn la_document_free(document: *mut LaDocument) {
+    if !document.is_null() {
+        drop(Box::from_raw(document));
+    }
+}
+
+/// Текст документа, уже разбитого на строки. Результат освобождается `la_result_free`; для документа NULL - NULL.
+///
+/// # Safety
+///
+/// `document` - NULL или действующий указатель из `la_document_new` или `la_document_new_with`.
+#[no_mangle]
+pub unsafe extern "C" fn la_document_format(document: *const LaDocument) -> *mut LaResult {
+    let Some(document) = document.as_ref() else {
+        return ptr::null_mut();
+    };
+    let result: LaResult = match &document.document {
+        Ok(document) => LaResult { ok: true, text: c_string(document.format_to_string()) },
+        Err(error) => LaResult { ok: false, text: c_string(error.to_string()) },
+    };
+    Box::into_raw(Box::new(result))
+}
+
+/// Удалось ли выравнивание; для NULL - false.
+///
+/// # Safety
+///
+/// `result` - NULL или действующий указатель из `la_document_format`.
+#[no_mangle]
+pub unsafe extern "C" fn la_result_ok(result: *const LaResult) -> bool {
+    result.as_ref().is_some_and(|result| result.ok)
+}
+
+/// Выровненный текст или сообщение об ошибке, действителен до `la_result_free`; для NULL - NULL.
+///
+/// # Safety
+///
+/// `result` - NULL или действующий указатель из `la_document_format`.
+#[no_mangle]
+pub unsafe extern "C" fn la_result_text(result: *const LaResult) -> *const c_char {
+    result.as_ref().map_or(ptr::null(), |result| result.text.as_ptr())
+}
+
+/// Освобождает результат из `la_document_format`.
+///
+/// # Safety
+///
+/// `result` - NULL или ещё не освобождённый указатель из `la_document_format`.
+#[no_mangle]
+pub unsafe extern "C" fn la_result_free(result: *mut LaResult) {
+    if !result.is_null() {
+        drop(Box::from_raw(result));
+    }
+}
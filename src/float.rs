@@ -0,0 +1,21 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::wrap;
+use crate::BreakAlgorithm;
+
+pub fn wrap_f32(word_widths: &[f32], space_width: f32, line_width: f32, break_algorithm: BreakAlgorithm) -> Vec<Range<usize>> {   // Строки из слов с заданными ширинами (например, в точках пропорционального шрифта)
+    let clean = |width: f32| if width.is_finite() { width.max(0.0) } else { 0.0 };   // Отрицательные и нечисловые ширины не ломают разбиение
+    let space_width: f32 = clean(space_width);
+    let items: Vec<wrap::Item<f32>> =
+        word_widths.iter()
+            .map(|&width| wrap::Item { width: clean(width), whitespace: space_width, penalty: 0.0, glue: false, hang: 0.0 })
+            .collect();
+    let line_width: f32 = clean(line_width);
+    let line_width = |_: usize| line_width;
+    match break_algorithm {
+        BreakAlgorithm::Greedy => wrap::greedy(&items, &line_width),
+        BreakAlgorithm::Optimal => wrap::optimal(&items, &line_width),
+        BreakAlgorithm::Balanced => wrap::balanced(&items, &line_width),
+    }
+}
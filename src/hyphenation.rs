@@ -0,0 +1,240 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+
+use crate::WordSplitter;
+
+fn tex_group<'t>(text: &'t str, command: &str) -> Option<&'t str> {                // Содержимое "\command{...}" без вложенных скобок
+    let start: usize = text.find(command)? + command.len();
+    let body: &str = text[start..].trim_start().strip_prefix('{')?;
+    body.find('}').map(|end| &body[..end])
+}
+
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct Recent {
+    entries: HashMap<String, (Vec<usize>, u64)>,                                    // Слово -> места переноса и время последнего обращения
+    order: BTreeMap<u64, String>,                                                   // Время обращения -> слово; первое - давно не нужное
+    clock: u64,
+}
+
+#[cfg(feature = "std")]
+struct Cache {                                                                      // Последние capacity слов с их местами переноса (LRU)
+    capacity: usize,                            // 0 - без кэша
+    recent: Mutex<Recent>,
+}
+
+#[cfg(feature = "std")]
+impl Cache {
+    fn new(capacity: usize) -> Cache {
+        Cache { capacity, recent: Mutex::default() }
+    }
+    fn get(&self, word: &str, compute: impl FnOnce() -> Vec<usize>) -> Vec<usize> {
+        if self.capacity == 0 {
+            return compute();
+        }
+        let mut recent = self.recent.lock().unwrap_or_else(|poisoned| poisoned.into_inner());   // Кэш не ломается паникой в другом потоке
+        recent.clock += 1;
+        let clock: u64 = recent.clock;
+        if let Some((points, used)) = recent.entries.get_mut(word) {
+            let (points, previous): (Vec<usize>, u64) = (points.clone(), core::mem::replace(used, clock));
+            recent.order.remove(&previous);
+            recent.order.insert(clock, String::from(word));
+            return points;
+        }
+        let points: Vec<usize> = compute();
+        if recent.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = recent.order.pop_first() {
+                recent.entries.remove(&oldest);
+            }
+        }
+        recent.entries.insert(String::from(word), (points.clone(), clock));
+        recent.order.insert(clock, String::from(word));
+        points
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clone for Cache {
+    fn clone(&self) -> Cache {                                                      // Копия начинает с пустого кэша той же ёмкости
+        Cache::new(self.capacity)
+    }
+}
+
+#[cfg(feature = "std")]
+impl PartialEq for Cache {
+    fn eq(&self, _: &Cache) -> bool {                                               // Кэш не влияет на результат
+        true
+    }
+}
+
+#[cfg(feature = "std")]
+impl Eq for Cache {}
+
+#[cfg(feature = "std")]
+impl core::fmt::Debug for Cache {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Cache").field("capacity", &self.capacity).finish()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Hyphenator {
+    patterns: BTreeMap<String, Vec<u8>>,                                            // Буквы шаблона -> веса позиций между ними (на одну больше, чем букв)
+    longest_pattern: usize,
+    exceptions: BTreeMap<String, Vec<usize>>,                                       // Слово строчными буквами -> номера букв, перед которыми перенос
+    min_before: usize,
+    min_after: usize,
+    #[cfg(feature = "std")]
+    cache: Cache,
+}
+
+impl Hyphenator {
+    pub fn sample_english() -> Hyphenator {                                         // Небольшой пример шаблонов английского (суффиксы, приставки, двойные согласные), а не полный hyph-en-us: большинство слов не переносится; настоящие шаблоны TeX загружаются через from_pattern_text
+        Hyphenator::from_pattern_list(include_str!("patterns/sample-en.txt"), 2, 3)
+    }
+    pub fn from_pattern_text(text: &str) -> Hyphenator {                            // Шаблоны TeX ("\\patterns{...}" в .tex) или просто через пробел (.pat); "%" - комментарий до конца строки
+        let text: String = text.lines().map(|line| line.split('%').next().unwrap_or("")).collect::<Vec<&str>>().join("\n");
+        let mut hyphenator: Hyphenator = Hyphenator::from_pattern_list(tex_group(&text, "\\patterns").unwrap_or(&text), 2, 3);
+        for word in tex_group(&text, "\\hyphenation").unwrap_or("").split_whitespace() {
+            hyphenator.add_exception(word);
+        }
+        hyphenator
+    }
+    pub fn min_before(mut self, min_before: usize) -> Hyphenator {                  // Наименьшее число букв перед переносом (не меньше 1)
+        self.min_before = min_before;
+        self.clear_cache();
+        self
+    }
+    pub fn min_after(mut self, min_after: usize) -> Hyphenator {                    // Наименьшее число букв после переноса (не меньше 1)
+        self.min_after = min_after;
+        self.clear_cache();
+        self
+    }
+    #[cfg(feature = "std")]
+    pub fn with_cache(mut self, capacity: usize) -> Hyphenator {                    // Запоминает переносы последних capacity слов - для пакетной обработки текстов с общим словарём; 0 - без кэша
+        self.cache = Cache::new(capacity);
+        self
+    }
+    fn clear_cache(&mut self) {
+        #[cfg(feature = "std")]
+        {
+            self.cache = self.cache.clone();
+        }
+    }
+    pub fn add_exception(&mut self, hyphenated: &str) {                             // Слово с явными переносами ("ta-ble") вместо шаблонов; без дефисов - не переносится
+        let mut letters: String = String::with_capacity(hyphenated.len());
+        let mut points: Vec<usize> = Vec::new();
+        for c in hyphenated.chars() {
+            if c == '-' {
+                points.push(letters.chars().count());
+            } else {
+                letters.extend(c.to_lowercase());
+            }
+        }
+        points.retain(|&point| point > 0 && point < letters.chars().count());
+        points.dedup();
+        self.exceptions.insert(letters, points);
+        self.clear_cache();
+    }
+    #[cfg(feature = "std")]
+    pub fn from_patterns<R: io::Read>(mut reader: R) -> io::Result<Hyphenator> {   // Файл шаблонов для языков, которых нет в библиотеке
+        let mut text: String = String::new();
+        reader.read_to_string(&mut text)?;
+        Ok(Hyphenator::from_pattern_text(&text))
+    }
+    fn from_pattern_list(list: &str, min_before: usize, min_after: usize) -> Hyphenator {
+        let mut hyphenator: Hyphenator =
+            Hyphenator {
+                patterns: BTreeMap::new(),
+                longest_pattern: 0,
+                exceptions: BTreeMap::new(),
+                min_before,
+                min_after,
+                #[cfg(feature = "std")]
+                cache: Cache::new(0),
+            };
+        for pattern in list.split_whitespace() {
+            hyphenator.add_pattern(pattern);
+        }
+        hyphenator
+    }
+    fn add_pattern(&mut self, pattern: &str) {
+        let mut letters: String = String::with_capacity(pattern.len());
+        let mut weights: Vec<u8> = Vec::with_capacity(pattern.len() + 1);
+        let mut pending: u8 = 0;
+        for c in pattern.chars() {
+            match c.to_digit(10) {
+                Some(digit) => pending = digit as u8,
+                None => {
+                    letters.push(c);
+                    weights.push(pending);
+                    pending = 0;
+                },
+            }
+        }
+        weights.push(pending);
+        self.longest_pattern = self.longest_pattern.max(letters.chars().count());
+        self.patterns.insert(letters, weights);
+    }
+    pub fn hyphenate(&self, word: &str) -> Vec<usize> {                             // Байтовые смещения внутри слова, в которых допустим перенос
+        #[cfg(feature = "std")]
+        return self.cache.get(word, || self.hyphenate_uncached(word));
+        #[cfg(not(feature = "std"))]
+        self.hyphenate_uncached(word)
+    }
+    fn hyphenate_uncached(&self, word: &str) -> Vec<usize> {
+        let is_punctuation = |c: char| !c.is_alphabetic();
+        let core_start: usize = word.len() - word.trim_start_matches(is_punctuation).len();
+        let core: &str = word.trim_matches(is_punctuation);
+        if core.is_empty() || !core.chars().all(char::is_alphabetic) {
+            return Vec::new();
+        }
+
+        let offsets: Vec<usize> = core.char_indices().map(|(index, _)| core_start + index).collect();
+        let letters: Vec<char> =
+            Some('.').into_iter()
+                .chain(core.chars().flat_map(char::to_lowercase))
+                .chain(Some('.'))
+                .collect();
+        if letters.len() != offsets.len() + 2 {                                     // Регистр изменил число символов - не рискуем
+            return Vec::new();
+        }
+        if let Some(points) = self.exceptions.get(&letters[1..letters.len() - 1].iter().collect::<String>()) {
+            return points.iter().map(|&position| offsets[position]).collect();
+        }
+
+        let mut weights: Vec<u8> = vec![0; letters.len() + 1];
+        let mut key: String = String::new();
+        for start in 0..letters.len() {
+            key.clear();
+            for &letter in letters[start..].iter().take(self.longest_pattern) {
+                key.push(letter);
+                if let Some(pattern) = self.patterns.get(&key) {
+                    for (shift, &weight) in pattern.iter().enumerate() {
+                        weights[start + shift] = weights[start + shift].max(weight);
+                    }
+                }
+            }
+        }
+
+        let letter_count: usize = offsets.len();
+        (self.min_before.max(1)..=letter_count.saturating_sub(self.min_after.max(1)))
+            .filter(|&position| weights[position + 1] % 2 == 1)
+            .map(|position| offsets[position])
+            .collect()
+    }
+}
+
+impl WordSplitter for Hyphenator {
+    fn split_points(&self, word: &str) -> Vec<(usize, bool)> {
+        self.hyphenate(word).into_iter().map(|point| (point, true)).collect()
+    }
+}
@@ -0,0 +1,41 @@
+use alloc::string::String;
+
+pub(crate) const TATWEEL: char = '\u{0640}';
+
+const NON_JOINING_AFTER: &[char] = &[                                                // Буквы, не соединяющиеся со следующей: после них кашиду не вставить
+    'ء', 'آ', 'أ', 'ؤ', 'إ', 'ا', 'ة', 'د', 'ذ', 'ر', 'ز', 'و', 'ى', 'ٱ', 'ژ',
+];
+
+fn is_arabic_letter(c: char) -> bool {
+    matches!(c, '\u{0620}'..='\u{063F}' | '\u{0641}'..='\u{064A}' | '\u{066E}'..='\u{06D3}' | '\u{06FA}'..='\u{06FC}')
+}
+
+fn is_mark(c: char) -> bool {                                                       // Огласовки стоят после своей буквы
+    matches!(c, '\u{0610}'..='\u{061A}' | '\u{064B}'..='\u{065F}' | '\u{0670}' | '\u{06D6}'..='\u{06ED}')
+}
+
+pub(crate) fn join_points(word: &str) -> impl Iterator<Item = usize> + '_ {        // Байты, перед которыми можно вставить кашиду: между двумя соединёнными буквами
+    let mut previous: Option<char> = None;
+    word.char_indices().filter_map(move |(index, c)| {
+        if is_mark(c) {
+            return None;
+        }
+        let joins: bool = previous.is_some_and(|previous| is_arabic_letter(previous) && !NON_JOINING_AFTER.contains(&previous)) && is_arabic_letter(c);
+        previous = Some(c);
+        joins.then_some(index)
+    })
+}
+
+pub(crate) fn stretched(word: &str, first_point: usize, kashidas: usize, point_count: usize) -> String {   // Слово с кашидами; точки соединения нумеруются по всей строке с first_point
+    let mut text: String = String::with_capacity(word.len());
+    let mut start: usize = 0;
+    for (number, point) in join_points(word).enumerate() {
+        let point_number: usize = first_point + number;
+        let count: usize = kashidas * (point_number + 1) / point_count - kashidas * point_number / point_count;   // Равномерно по всей строке
+        text.push_str(&word[start..point]);
+        text.extend(core::iter::repeat_n(TATWEEL, count));
+        start = point;
+    }
+    text.push_str(&word[start..]);
+    text
+}
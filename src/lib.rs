@@ -1,154 +1,4097 @@
-use core::slice::Iter;
-use std::str;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-#[derive(PartialEq, Eq, Debug)]
+extern crate alloc;
+
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+use core::str;
+#[cfg(feature = "std")]
+use std::io;
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+mod bidi;
+mod cjk;
+mod editor;
+mod float;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "hyphenation")]
+mod hyphenation;
+mod kashida;
+mod markdown;
+mod options;
+mod owned;
+mod quality;
+#[cfg(feature = "fast-tokenizer")]
+mod scan;
+mod segment;
+mod split;
+mod token;
+mod verify;
+mod warning;
+mod width;
+mod wrap;
+
+#[cfg(feature = "arbitrary")]
+pub use arbitrary::Generator;
+pub use editor::Editor;
+pub use float::wrap_f32;
+#[cfg(feature = "hyphenation")]
+pub use hyphenation::Hyphenator;
+pub use options::{Alignment, BreakAlgorithm, Direction, Formatter, GapStrategy, LastLineMode, LineEnding, Locale, NewlinePolicy, Options, OverflowPolicy, TabPolicy, WordPattern};
+pub use owned::DocumentOwned;
+pub use quality::Quality;
+#[cfg(feature = "dictionary")]
+pub use segment::DictionarySegmenter;
+pub use segment::Segmenter;
+pub use split::{CharSplitter, HyphenSplitter, UrlSplitter, WordSplitter};
+pub use token::{Tokenizer, WhitespaceTokenizer};
+pub use verify::{verify, Violation};
+pub use warning::Warning;
+pub use width::{Measure, WidthMode};
+pub use wrap::badness;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[non_exhaustive]
 pub enum DocError {
-    WordTooLong,
+    WordTooLong {                               // Слово не помещается в строку (OverflowPolicy::Error)
+        word: String,
+        range: Range<usize>,                    // Байты слова во входном тексте
+        word_index: usize,                      // Сколько слов текста предшествует этому
+    },
+    ZeroWidth,
+    WidthTooSmall {                             // В строку не помещается даже один символ со знаком переноса
+        line_width: usize,
+        minimum: usize,
+    },
+    Tab {                                       // Табуляция во входном тексте (TabPolicy::Error)
+        offset: usize,                          // Байт во входном тексте
+    },
+    TooManyCells {                              // В ряду transform_table больше ячеек, чем ширин колонок
+        row: usize,                             // Номер ряда с нуля
+        cells: usize,
+        columns: usize,
+    },
 }
 
-struct Line<'a> {
-    words: Vec<&'a str>,
-    char_counter: u32,
+impl fmt::Display for DocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DocError::WordTooLong { word, range, word_index } =>
+                write!(f, "word {:?} (word {} at bytes {}..{}) does not fit into the line width", word, word_index + 1, range.start, range.end),
+            DocError::ZeroWidth => f.write_str("line width must be greater than zero"),
+            DocError::WidthTooSmall { line_width, minimum } =>
+                write!(f, "line width {} is smaller than the minimum of {}", line_width, minimum),
+            DocError::Tab { offset } => write!(f, "tab character at byte {} is not allowed", offset),
+            DocError::TooManyCells { row, cells, columns } =>
+                write!(f, "row {} has {} cells, but only {} column widths are given", row + 1, cells, columns),
+        }
+    }
 }
 
-impl<'a> Line<'a> {
-    fn new_with_word(word: &'a str) -> Line<'a> {
-        let char_counter: u32 = word.len() as u32;
-        Line::<'_>{
-            words: vec![word],
-            char_counter,
+impl core::error::Error for DocError {}
+
+impl DocError {
+    pub(crate) fn shifted(self, bytes: usize, words: usize) -> DocError {          // Переводит положение из куска текста в положение во всём тексте
+        match self {
+            DocError::WordTooLong { word, range, word_index } =>
+                DocError::WordTooLong { word, range: range.start + bytes..range.end + bytes, word_index: word_index + words },
+            DocError::Tab { offset } => DocError::Tab { offset: offset + bytes },
+            error => error,
         }
     }
-    fn add_word(&mut self, word: &'a str) {
-        self.char_counter += word.len() as u32;
-        self.words.push(word);
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum StreamError {                                                              // Ошибка потоковой обработки: чтения/записи или форматирования
+    Io(io::Error),
+    Doc(DocError),
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::Io(error) => error.fmt(f),
+            StreamError::Doc(error) => error.fmt(f),
+        }
     }
-    fn word_fits(&self, word: &'a str, line_width: u32) -> bool {
-        self.char_count() + self.word_count() + word.len() as u32 <= line_width
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StreamError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StreamError::Io(error) => Some(error),
+            StreamError::Doc(error) => Some(error),
+        }
     }
-    fn char_count(&self) -> u32 {
-        self.char_counter
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for StreamError {
+    fn from(error: io::Error) -> StreamError {
+        StreamError::Io(error)
     }
-    fn word_count(&self) -> u32 {
-        self.words.len() as u32
+}
+
+#[cfg(feature = "std")]
+impl From<DocError> for StreamError {
+    fn from(error: DocError) -> StreamError {
+        StreamError::Doc(error)
     }
-    fn iter(&self) -> Iter<'a, &str> {
-        self.words.iter()
+}
+
+pub(crate) const SOFT_HYPHEN: char = '\u{AD}';
+const NARROW_NO_BREAK_SPACE: &str = "\u{202F}";
+const FRENCH_BEFORE: &[char] = &[';', ':', '!', '?', '»'];                          // Знаки, перед которыми во французском наборе узкий неразрывный пробел
+const HANGING: &[char] = &[',', '.', '-', '\u{2010}', '"', '\'', '”', '’'];                     // Знаки, выступающие за правый край при Options::hanging_punctuation
+const WORD_JOINERS: &[char] = &['\u{2060}', '\u{FEFF}', '\u{200C}', '\u{200D}'];          // Невидимые знаки, запрещающие разрыв строки рядом с собой (включая ZWNJ и ZWJ)
+const ZERO_WIDTH_SPACE: char = '\u{200B}';                                          // Место разрыва внутри слова без знака переноса
+
+fn joined_at(text: &str, point: usize) -> bool {                                    // Стоит ли у места разрыва внутри слова знак из WORD_JOINERS или перед ним - селектор варианта
+    text[..point].ends_with(WORD_JOINERS) || text[point..].starts_with(WORD_JOINERS)
+        || text[point..].chars().next().is_some_and(|c| c != ZERO_WIDTH_SPACE && width::is_zero_width(c))
+}
+
+fn is_breaking_whitespace(c: char) -> bool {
+    c.is_whitespace() && !matches!(c, '\u{A0}' | '\u{2007}' | '\u{202F}')           // Неразрывные пробелы склеивают соседние слова в одно
+}
+
+fn line_body<'a>(line: &'a str, options: &Options) -> &'a str {                    // Строка без Options::prefix (или без его пробелов в конце)
+    if options.prefix.is_empty() {
+        return line;
     }
+    line.strip_prefix(options.prefix.as_str())
+        .or_else(|| line.strip_prefix(options.prefix.trim_end()))
+        .unwrap_or(line)
 }
 
+fn is_blank(line: &str, options: &Options) -> bool {
+    line_body(line, options).trim().is_empty()
+}
 
-pub struct Document<'a> {
-    lines: Vec<Line<'a>>,
-    line_width: u32,
+pub(crate) fn separator_prefix<'o>(piece: &str, options: &'o Options) -> &'o str {  // Префикс строки разделителя: у пустых строк - без пробелов в конце
+    if piece.is_empty() { options.prefix.trim_end() } else { &options.prefix }
 }
 
-impl<'a> Document<'a> {
-    fn add_word(&mut self, word: &'a str) {
-        let create_new_line: bool = self.lines.last().map_or(true, |line| !line.word_fits(word, self.line_width));
-        if create_new_line {
-            self.lines.push( Line::new_with_word(word) );
-        } else if let Some(line) = self.lines.last_mut() {
-            line.add_word(word);
-        }
-    }
-    pub fn from_str(input: &str, line_width: u32) -> Result<Document, DocError> {
-        let mut doc: Document =
-            Document {
-                lines: Vec::new(),
-                line_width,
-            };
-        
-        for word in input.split_whitespace() {
-            if word.len() as u32 > line_width {
-                return Err(DocError::WordTooLong)
+fn paragraph_ranges(input: &str, options: &Options) -> Vec<Range<usize>> {         // Абзацы разделены хотя бы одной пустой строкой
+    if options.newlines == NewlinePolicy::Collapse {
+        return if input.lines().all(|line| is_blank(line, options)) { Vec::new() } else { Some(0..input.len()).into_iter().collect() };
+    }
+
+    let mut paragraphs: Vec<Range<usize>> = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut offset: usize = 0;
+    let mut fence: markdown::Fence = markdown::Fence::default();
+    for line in input.split_inclusive('\n') {
+        let fenced: bool = options.markdown && fence.line(line_body(line, options));   // Пустые строки внутри блока кода абзац не разделяют
+        if !fenced && is_blank(line, options) {
+            if let Some(start) = start.take() {
+                paragraphs.push(start..offset);
             }
-            doc.add_word(word);
-        };
+        } else if start.is_none() {
+            start = Some(offset);
+        }
+        offset += line.len();
+    }
+    if let Some(start) = start {
+        paragraphs.push(start..input.len());
+    }
+    paragraphs
+}
+
+fn paragraphs<'a>(input: &'a str, options: &Options) -> Vec<&'a str> {
+    paragraph_ranges(input, options).into_iter().map(|range| &input[range]).collect()
+}
+
+#[cfg(not(feature = "fast-tokenizer"))]
+fn words(input: &str) -> impl Iterator<Item = &str> {
+    input.split(is_breaking_whitespace).filter(|word| !word.is_empty())
+}
+
+#[cfg(feature = "fast-tokenizer")]
+fn words(input: &str) -> impl Iterator<Item = &str> {                               // Побайтовый просмотр с быстрым путём для ASCII
+    scan::Words::new(input)
+}
+
+fn block_words<'a>(text: &'a str, options: &Options) -> Vec<&'a str> {              // Слова по Options::tokenizer; срезы не из text и пустые отбрасываются
+    let Some(tokenizer) = &options.tokenizer else {
+        return words(text).collect();
+    };
+    let range: Range<usize> = text.as_ptr() as usize..text.as_ptr() as usize + text.len();
+    tokenizer.words(text)
+        .into_iter()
+        .filter(|word| !word.is_empty() && range.start <= word.as_ptr() as usize && word.as_ptr() as usize + word.len() <= range.end)
+        .collect()
+}
+
+pub(crate) fn is_stripped_control(c: char, options: &Options) -> bool {
+    options.strip_control && c.is_control() && !c.is_whitespace() && !(options.ignore_ansi && c == width::ESCAPE)
+}
 
-        Ok(doc)
+pub(crate) fn is_hidden(c: char, options: &Options) -> bool {                      // Символы, которые не выводятся и не занимают места
+    c == SOFT_HYPHEN || is_stripped_control(c, options)
+}
+
+fn write_visible<W: fmt::Write>(out: &mut W, word: &str, options: &Options) -> fmt::Result {
+    if word.chars().any(|c| is_hidden(c, options)) {
+        word.split(|c| is_hidden(c, options)).try_for_each(|part| out.write_str(part))
+    } else {
+        out.write_str(word)
     }
-    pub fn format_to_string(&self) -> String {
-        
-        let add_whitespaces = |s: &mut String, count| {
-            for _ in 0..count {
-                s.push(' ');
+}
+
+fn visible_length(word: &str, options: &Options) -> usize {                        // Длина в байтах того, что запишет write_visible
+    word.len() - word.chars().filter(|&c| is_hidden(c, options)).map(char::len_utf8).sum::<usize>()
+}
+
+fn mix(value: u64) -> u64 {                                                         // Перемешивание splitmix64 для воспроизводимого псевдослучайного выбора
+    let value: u64 = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let value: u64 = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    let value: u64 = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^ (value >> 31)
+}
+
+fn hanging_width(text: &str, options: &Options) -> usize {                         // Ширина выступающего знака в конце текста
+    match text.chars().next_back() {
+        Some(c) if HANGING.contains(&c) => width::text_width(c.encode_utf8(&mut [0; 4]), options),
+        _ => 0,
+    }
+}
+
+fn french_gap(word: &str, next: &str) -> bool {                                     // "mot !", "« mot" - промежуток Locale::French
+    next.starts_with(FRENCH_BEFORE) || word.ends_with('«')
+}
+
+fn ends_sentence(word: &str) -> bool {                                              // Слово с точкой, "?" или "!" в конце, возможно перед закрывающими кавычками и скобками
+    word.trim_end_matches(['"', '\'', ')', ']', '»', '”', '’']).ends_with(['.', '?', '!', '…'])
+}
+
+fn check_tabs(input: &str, options: &Options) -> Result<(), DocError> {
+    match (options.tabs, input.find('\t')) {
+        (TabPolicy::Error, Some(offset)) => Err(DocError::Tab { offset }),
+        _ => Ok(()),
+    }
+}
+
+fn advance(column: usize, c: char, options: &Options) -> usize {                  // Позиция после символа, с учётом табуляции TabPolicy::ExpandTo
+    match (c, options.tabs) {
+        ('\t', TabPolicy::ExpandTo(stop)) => (column / stop.max(1) + 1) * stop.max(1),
+        _ => column + 1,
+    }
+}
+
+fn expand_tabs<'w>(word: &'w str, options: &Options) -> Cow<'w, str> {            // Строка, выводимая как есть, с табуляцией TabPolicy::ExpandTo, заменённой пробелами
+    if !matches!(options.tabs, TabPolicy::ExpandTo(_)) || !word.contains('\t') {
+        return Cow::Borrowed(word);
+    }
+    let mut expanded: String = String::with_capacity(word.len());
+    let mut column: usize = 0;
+    for c in word.chars() {
+        let next: usize = advance(column, c, options);
+        match c {
+            '\t' => expanded.extend(core::iter::repeat_n(' ', next - column)),
+            _ => expanded.push(c),
+        }
+        column = next;
+    }
+    Cow::Owned(expanded)
+}
+
+fn preserved_spaces(sources: &[(usize, &str)], word: &str, next: &str, options: &Options) -> usize {   // На сколько пробелов промежуток между соседними во входной строке словами шире min_gap при !Options::collapse_whitespace
+    if options.collapse_whitespace || options.alignment == Alignment::Justify {
+        return 0;
+    }
+    let (end, start): (usize, usize) = (word.as_ptr() as usize + word.len(), next.as_ptr() as usize);
+    let Some(&(_, source)) = sources.iter().find(|&&(_, source)| source.as_ptr() as usize <= end && start <= source.as_ptr() as usize + source.len()) else {
+        return 0;
+    };
+    let offset: usize = source.as_ptr() as usize;
+    match source.get(end - offset..start.max(end) - offset) {
+        Some(gap) if !gap.contains(['\n', '\r']) => {
+            let line_start: usize = source[..end - offset].rfind('\n').map_or(0, |index| index + 1);
+            let column: usize = source[line_start..end - offset].chars().fold(0, |column, c| advance(column, c, options));
+            (gap.chars().fold(column, |column, c| advance(column, c, options)) - column).saturating_sub(options.min_gap.max(1))
+        }
+        _ => 0,                                                                     // Между строками ввода и между кусками текста - обычный промежуток
+    }
+}
+
+fn letter_points(word: &str) -> usize {                                             // Места между кластерами графем слова
+    width::Graphemes::new(word).count().saturating_sub(1)
+}
+
+fn letter_spaced(word: &str, first_point: usize, spaces: usize, point_count: usize, fill: char) -> String {   // Слово с пробелами между буквами; места нумеруются по всей строке с first_point
+    let mut text: String = String::with_capacity(word.len() + spaces * fill.len_utf8());
+    for (number, cluster) in width::Graphemes::new(word).enumerate() {
+        if number > 0 {
+            let point: usize = first_point + number - 1;
+            text.extend(core::iter::repeat_n(fill, spaces * (point + 1) / point_count - spaces * point / point_count));
+        }
+        text.push_str(cluster);
+    }
+    text
+}
+
+fn write_whitespaces<W: fmt::Write>(out: &mut W, count: usize, fill: char) -> fmt::Result {
+    const WHITESPACES: &str = "                                ";
+    if fill != ' ' {
+        return (0..count).try_for_each(|_| out.write_char(fill));
+    }
+    let mut rest: usize = count;
+    while rest > 0 {
+        let chunk: usize = rest.min(WHITESPACES.len());                             // Пишем пробелы кусками, а не по одному символу
+        out.write_str(&WHITESPACES[..chunk])?;
+        rest -= chunk;
+    }
+    Ok(())
+}
+
+fn write_paragraph_break<W: fmt::Write>(out: &mut W, options: &Options) -> fmt::Result {   // Перевод строки и разделитель абзацев с переводами строк из Options::line_ending
+    let ending: &str = options.line_ending.as_str();
+    out.write_str(ending)?;
+    let mut pieces = options.paragraph_separator.split('\n').peekable();
+    while let Some(piece) = pieces.next() {
+        if pieces.peek().is_none() {
+            return out.write_str(piece);                                            // Хвост без перевода строки начинает следующую строку
+        }
+        out.write_str(separator_prefix(piece, options))?;
+        out.write_str(piece)?;
+        out.write_str(ending)?;
+    }
+    Ok(())
+}
+
+fn paragraph_break_length(options: &Options) -> usize {
+    let ending: usize = options.line_ending.as_str().len();
+    let mut pieces: Vec<&str> = options.paragraph_separator.split('\n').collect();
+    let tail: usize = pieces.pop().map_or(0, str::len);
+    ending + tail + pieces.into_iter().map(|piece| separator_prefix(piece, options).len() + piece.len() + ending).sum::<usize>()
+}
+
+enum Piece<'s, 'p> {                                                                   // Кусок строки вывода
+    Blank(&'s str),                             // Префикс, отступ, узкий пробел - без места в Document::layout
+    Text(&'s str),                              // Маркер списка, знак переноса, многоточие
+    Spaces(usize, char),
+    Word(&'s str, &'p str),                     // Слово документа и его вид в выводе (с кашидами, разрядкой, перевёрнутое)
+}
+
+#[cfg(feature = "std")]
+struct IoAdapter<'w, W: io::Write> {                                                // Позволяет писать через fmt::Write в io::Write, сохраняя исходную ошибку
+    inner: &'w mut W,
+    error: Option<io::Error>,
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> fmt::Write for IoAdapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
+
+struct Matcher<'i> {                                                                // Сверяет вывод с исходным текстом, ничего не выделяя
+    rest: &'i str,
+}
+
+impl fmt::Write for Matcher<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match self.rest.strip_prefix(s) {
+            Some(rest) => {
+                self.rest = rest;
+                Ok(())
             }
-        };
+            None => Err(fmt::Error),
+        }
+    }
+}
 
-        let text_capacity: usize =
-            match self.lines.len() {
-                0 => 0,
-                1 => self.line_width as usize * 2,                                              // Умножаем на 2 - предусматриваем место для Utf-8 символов, пренебрегая экзотическими символами
-                _ => self.lines.len() * self.line_width as usize * 2 + self.lines.len() - 1,    // Умножаем на 2 - предусматриваем место для Utf-8 символов, пренебрегая экзотическими символами
-            };
-        let mut text: String = String::with_capacity(text_capacity);                            // Заранее выделяем достаточно места, чтобы избежать лишнего релоцирования данных
+struct Fragment<'a> {
+    word: &'a str,
+    range: Range<usize>,                        // Часть слова, попадающая во фрагмент
+    width: usize,
+    word_end: bool,
+    hyphen: bool,                               // При разрыве строки после фрагмента добавляется дефис
+    unit_end: bool,                             // Фрагмент - отдельное слово строки без пробела после него (символы CJK)
+}
 
-        for (line_number, line) in self.lines.iter().enumerate() {
-            if line.word_count() > 1 {
-                let (base_witespace_width, extra_witespace, gap_count): (u32,u32,u32) = {
-                    let whitespace_count: u32 = self.line_width - line.char_count();
-                    let gap_count: u32 = line.word_count() - 1;
-                    (whitespace_count / gap_count, whitespace_count % gap_count, gap_count)
-                };
+impl<'a> Fragment<'a> {
+    fn new(word: &'a str, range: Range<usize>, word_end: bool, hyphen: bool, options: &Options) -> Fragment<'a> {
+        Fragment {
+            width: width::text_width(&word[range.clone()], options),
+            word,
+            range,
+            word_end,
+            hyphen,
+            unit_end: false,
+        }
+    }
+    fn split_word(word: &'a str, line_width: usize, options: &Options) -> Vec<Fragment<'a>> {
+        if options.overflow == OverflowPolicy::Truncate && width::text_width(word, options) > line_width {
+            let end: usize = width::fit_prefix(word, line_width, options);
+            return vec![Fragment::new(word, 0..end, true, false, options)];
+        }
 
-                for (word_number, word) in line.iter().enumerate() {
-                    text.push_str(word);
-
-                    if (word_number as u32) < gap_count {
-                        let whitespaces: u32 =
-                            if (word_number as u32) < extra_witespace {
-                                base_witespace_width + 1
-                            } else {
-                                base_witespace_width
-                            };
-                        add_whitespaces(&mut text, whitespaces);
-                    }
+        let escapes: Vec<Range<usize>> = width::escape_ranges(word, options);
+        let url: bool =
+            if escapes.is_empty() {
+                split::is_url(word)
+            } else {
+                let mut visible: String = String::with_capacity(word.len());       // Адрес внутри управляющей последовательности (ссылка OSC 8) - не текст слова
+                let mut start: usize = 0;
+                for range in escapes.iter().cloned().chain(Some(word.len()..word.len())) {
+                    visible.push_str(&word[start..range.start]);
+                    start = range.end;
                 }
+                split::is_url(&visible)
+            };
+        let mut split_points: Vec<(usize, bool)> =                                 // (место, нужен ли знак переноса)
+            if url {
+                Vec::new()                                                          // Адреса не переносятся со знаком переноса
+            } else if word.contains(SOFT_HYPHEN) {                                  // Явные мягкие переносы отменяют автоматическую расстановку
+                word.match_indices(SOFT_HYPHEN)
+                    .map(|(index, _)| index + SOFT_HYPHEN.len_utf8())
+                    .filter(|&index| index < word.len() && width::text_width(&word[..index], options) > 0)
+                    .map(|index| (index, true))
+                    .collect()
             } else {
-                let whitespaces: u32 = self.line_width - line.char_count();
-                if let Some(word) = line.iter().next() {
-                    text.push_str(word);
+                #[cfg(feature = "hyphenation")]
+                { options.hyphenator.as_ref().map_or_else(Vec::new, |hyphenator| hyphenator.split_points(word)) }
+                #[cfg(not(feature = "hyphenation"))]
+                { Vec::new() }
+            };
+        split_points.extend(
+            word.match_indices(ZERO_WIDTH_SPACE)
+                .map(|(index, _)| index + ZERO_WIDTH_SPACE.len_utf8())
+                .filter(|&index| width::text_width(&word[..index], options) > 0)
+                .map(|index| (index, false))                                        // Явные места разрыва без знака переноса
+        );
+        if options.break_at_hyphens {
+            split_points.extend(HyphenSplitter.split_points(word));
+        }
+        if url && !options.keep_urls {
+            split_points.extend(UrlSplitter.split_points(word));
+        }
+        for splitter in &options.splitters {
+            split_points.extend(splitter.split_points(word));
+        }
+        split_points.sort_unstable();                                              // Разрыв без знака переноса идёт первым и остаётся
+        split_points.dedup_by_key(|&mut (point, _)| point);
+        split_points.retain(|&(point, _)| {
+            point > 0 && point < word.len() && word.is_char_boundary(point)
+                && !joined_at(word, point) && !escapes.iter().any(|range| range.start < point && point < range.end)
+        });
+        let mut fragments: Vec<Fragment> = Vec::with_capacity(split_points.len() + 1);
+        let mut start: usize = 0;
+        for (end, hyphen) in split_points.into_iter().chain(Some((word.len(), false))) {
+            fragments.push(Fragment::new(word, start..end, end == word.len(), hyphen, options));
+            start = end;
+        }
+
+        if options.cjk || options.segmenter.is_some() {
+            fragments = fragments.into_iter()
+                .flat_map(|fragment| fragment.split_units(options))
+                .collect();
+        }
+        if options.overflow == OverflowPolicy::BreakAnywhere && !(url && options.keep_urls) {
+            fragments = fragments.into_iter()
+                .flat_map(|fragment| fragment.break_anywhere(line_width, options))
+                .collect();
+        }
+        fragments
+    }
+    fn break_anywhere(self, line_width: usize, options: &Options) -> Vec<Fragment<'a>> {
+        let penalty: usize = if self.hyphen { width::text_width(&options.hyphen, options) } else { 0 };
+        if self.width.saturating_add(penalty) <= line_width {
+            return vec![self];
+        }
+
+        let limit: usize = line_width.saturating_sub(penalty).max(1);
+        let text: &str = &self.word[self.range.clone()];
+        let escapes: Vec<Range<usize>> = width::escape_ranges(text, options);
+        let cuts: Vec<usize> =
+            CharSplitter.split_points(text).into_iter()
+                .map(|(point, _)| point)
+                .filter(|&point| !escapes.iter().any(|range| range.start < point && point < range.end))
+                .chain(Some(text.len()))
+                .collect();
+        let mut pieces: Vec<Fragment> = Vec::new();
+        let (mut start, mut cut): (usize, usize) = (0, 0);
+        while start < text.len() {
+            let mut end: usize = cuts[cut];                                         // Хотя бы один кластер графем, даже если он шире строки
+            let mut width: usize = width::text_width(&text[start..end], options);
+            cut += 1;
+            while let Some(&next) = cuts.get(cut) {
+                width = width.saturating_add(width::text_width(&text[end..next], options));
+                if width > limit {
+                    break;
                 }
-                add_whitespaces(&mut text, whitespaces);
+                end = next;
+                cut += 1;
             }
-            if line_number < self.lines.len() - 1 {
-                text.push('\n');
+            let last: bool = end == text.len();
+            let range: Range<usize> = self.range.start + start..self.range.start + end;
+            let mut piece: Fragment = Fragment::new(self.word, range, last && self.word_end, last && self.hyphen, options);
+            piece.unit_end = last && self.unit_end;
+            pieces.push(piece);
+            start = end;
+        }
+        pieces
+    }
+    fn split_units(self, options: &Options) -> Vec<Fragment<'a>> {                 // Делит фрагмент между символами CJK и словами Options::segmenter; промежутки между ними растягиваются при выравнивании
+        let text: &str = &self.word[self.range.clone()];
+        let mut points: Vec<usize> = if options.cjk { cjk::break_points(text) } else { Vec::new() };
+        if let Some(segmenter) = &options.segmenter {
+            points.extend(segmenter.boundaries(text).into_iter().filter(|&point| point > 0 && point < text.len() && text.is_char_boundary(point)));
+            points.sort_unstable();
+            points.dedup();
+        }
+        points.retain(|&point| !joined_at(text, point));
+        let mut pieces: Vec<Fragment> = Vec::new();
+        let mut start: usize = self.range.start;
+        for point in points {
+            let mut piece: Fragment = Fragment::new(self.word, start..self.range.start + point, false, false, options);
+            piece.unit_end = true;
+            pieces.push(piece);
+            start = self.range.start + point;
+        }
+        pieces.push(Fragment { range: start..self.range.end, width: width::text_width(&self.word[start..self.range.end], options), ..self });
+        pieces
+    }
+    fn item(&self, space_width: usize, hyphen_width: usize) -> wrap::Item {
+        wrap::Item {
+            width: self.width,
+            whitespace: if self.word_end { space_width } else { 0 },
+            penalty: if self.hyphen { hyphen_width } else { 0 },
+            glue: false,
+            hang: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum LineBreak {
+    Wrap,                                       // Строка перенесена по ширине
+    Hard,                                       // Перевод строки из исходного текста (NewlinePolicy::Preserve)
+    Paragraph,
+}
+
+#[derive(Clone)]
+struct Line {
+    words: Range<usize>,                        // Диапазон слов строки в общем хранилище Document::words
+    char_counter: usize,
+    hyphen: bool,
+    end: LineBreak,
+    paragraph_start: bool,                      // Первая строка абзаца: отступ Options::initial_indent, а не subsequent_indent
+    list: ListIndent,
+    verbatim: bool,                             // Единственное слово строки выводится как есть, без выравнивания
+    ellipsis: bool,                             // После слов пишется Options::ellipsis (последняя строка при Options::max_lines)
+    width: usize,                               // Ширина строки вместе с префиксом и отступами
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ListIndent {
+    None,
+    Marker { token: usize, width: usize },      // Первая строка пункта: маркер (номер слова) и пробел после него
+    Hanging(usize),                             // Продолжение пункта выравнивается под его текстом
+}
+
+impl ListIndent {
+    fn width(self) -> usize {
+        match self {
+            ListIndent::None => 0,
+            ListIndent::Marker { width, .. } | ListIndent::Hanging(width) => width,
+        }
+    }
+}
+
+impl Line {
+    fn from_fragments<'a>(fragments: &[Fragment<'a>], hyphen_width: usize, words: &mut Vec<&'a str>) -> Line {
+        let first_word: usize = words.len();
+        let mut word_start: Option<usize> = None;
+        for (index, fragment) in fragments.iter().enumerate() {
+            let start: usize = *word_start.get_or_insert(fragment.range.start);
+            if fragment.word_end || fragment.unit_end || index == fragments.len() - 1 {   // Склеиваем фрагменты одного слова, оказавшиеся на одной строке
+                words.push(&fragment.word[start..fragment.range.end]);
+                word_start = None;
             }
         }
+        let hyphen: bool = fragments.last().is_some_and(|fragment| !fragment.word_end && fragment.hyphen);
 
-        text
+        Line {
+            words: first_word..words.len(),
+            char_counter: fragments.iter().map(|fragment| fragment.width).fold(if hyphen { hyphen_width } else { 0 }, usize::saturating_add),
+            hyphen,
+            end: LineBreak::Wrap,
+            paragraph_start: false,
+            list: ListIndent::None,
+            verbatim: false,
+            ellipsis: false,
+            width: 0,
+        }
+    }
+    fn char_count(&self) -> usize {
+        self.char_counter
+    }
+    fn word_count(&self) -> usize {
+        self.words.len()
     }
 }
 
 
-pub fn transform(input: &str, line_width: u32) -> Result<String, DocError> {
-    Document::from_str(input, line_width)
-        .map(|document|
-            document.format_to_string()
-        )
+#[derive(Clone, Copy)]
+pub struct LineView<'d, 'a> {                                                       // Строка документа до расстановки промежутков
+    line: &'d Line,
+    words: &'d [&'a str],
+    fill: f64,
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::DocError;
+impl<'d, 'a> LineView<'d, 'a> {
+    pub fn words(&self) -> &'d [&'a str] {                                          // Слова как срезы входного текста (с мягкими переносами, если они были)
+        self.words
+    }
+    pub fn text_width(&self) -> usize {                                               // Суммарная ширина слов вместе со знаком переноса, без промежутков
+        self.line.char_count()
+    }
+    pub fn hyphenated(&self) -> bool {
+        self.line.hyphen
+    }
+    pub fn ends_paragraph(&self) -> bool {
+        self.line.end == LineBreak::Paragraph
+    }
+    pub fn fill(&self) -> f64 {                                                     // Доля ширины, занятая словами с обычными промежутками: от 0 до 1
+        self.fill
+    }
+}
 
-    use super::transform;
+type LaidOut<'a> = (Vec<&'a str>, Vec<Line>);                                      // Слова и строки, ссылающиеся на них диапазонами
 
-    #[test]
-    fn simple() {
-        let test_cases = [
-            ("", 5, ""),
-            ("test", 5, "test "),
-            ("Lorem ipsum dolor sit amet consectetur adipiscing elit sed do eiusmod tempor incididunt ut labore et dolore magna aliqua", 12,
-             "Lorem  ipsum\ndolor    sit\namet        \nconsectetur \nadipiscing  \nelit  sed do\neiusmod     \ntempor      \nincididunt  \nut labore et\ndolore magna\naliqua      "),
-            ("Lorem     ipsum    dolor", 17, "Lorem ipsum dolor"),
-        ];
+#[derive(Clone)]
+struct Block {                                                                      // Независимо разбиваемый кусок текста: абзац или строка исходника
+    tokens: Range<usize>,
+    end: LineBreak,
+    starts_paragraph: bool,
+    hanging: usize,                             // Ширина маркера списка с пробелом; если не 0, первое слово блока - маркер
+    verbatim: bool,                             // Строки блока выводятся как есть (Options::markdown, Options::preformatted)
+}
 
-        for &(input, line_width, expected) in &test_cases {
-            println!("input: '{}'", input);
-            assert_eq!(transform(input, line_width), Ok(expected.to_string()));
+fn is_list_marker(word: &str) -> bool {                                             // "-", "*", "+", "•" или номер вида "1." и "1)"
+    matches!(word, "-" | "*" | "+" | "\u{2022}")
+        || word.strip_suffix(['.', ')']).is_some_and(|number| !number.is_empty() && number.bytes().all(|byte| byte.is_ascii_digit()))
+}
+
+fn list_items<'a>(paragraph: &'a str, options: &Options) -> Vec<&'a str> {            // Каждый пункт списка начинает отдельный блок
+    let mut items: Vec<&str> = Vec::new();
+    let (mut start, mut offset): (usize, usize) = (0, 0);
+    for line in paragraph.split_inclusive('\n') {
+        if offset > start && block_words(line_body(line, options), options).first().is_some_and(|word| is_list_marker(word)) {
+            items.push(&paragraph[start..offset]);
+            start = offset;
+        }
+        offset += line.len();
+    }
+    items.push(&paragraph[start..]);
+    items
+}
+
+fn tokenize<'a>(input: &'a str, options: &Options) -> (Vec<&'a str>, Vec<Block>) {
+    let mut tokens: Vec<&str> = Vec::new();
+    let mut blocks: Vec<Block> = Vec::new();
+    for paragraph in paragraphs(input, options) {
+        let segments: Vec<(&str, bool)> = if options.markdown || options.preformatted.is_some() { markdown::segments(paragraph, options) } else { vec![(paragraph, false)] };
+        for (segment, verbatim) in segments {
+            if verbatim {                                                           // Каждая строка - одно слово, выводимое как есть
+                let start: usize = tokens.len();
+                tokens.extend(segment.lines().map(|line| line_body(line, options)));
+                blocks.push(Block { tokens: start..tokens.len(), end: LineBreak::Hard, starts_paragraph: true, hanging: 0, verbatim: true });
+                continue;
+            }
+
+            let paragraph_blocks: Vec<&str> =
+                if options.newlines == NewlinePolicy::Preserve {
+                    segment.lines().collect()
+                } else if options.list_items {
+                    list_items(segment, options)
+                } else {
+                    vec![segment]
+                };
+            for (index, block) in paragraph_blocks.into_iter().enumerate() {
+                let start: usize = tokens.len();
+                if options.prefix.is_empty() {
+                    tokens.extend(block_words(block, options));
+                } else {
+                    tokens.extend(block.lines().flat_map(|line| block_words(line_body(line, options), options)));
+                }
+                let hanging: usize =
+                    match &tokens[start..] {
+                        [marker, _, ..] if options.list_items && is_list_marker(marker) => width::text_width(marker, options).saturating_add(width::text_width(" ", options).max(1)),
+                        _ => 0,
+                    };
+                blocks.push(Block { tokens: start..tokens.len(), end: LineBreak::Hard, starts_paragraph: index == 0 || hanging > 0, hanging, verbatim: false });
+            }
+        }
+        if let Some(block) = blocks.last_mut() {
+            block.end = LineBreak::Paragraph;
+        }
+    }
+    (tokens, blocks)
+}
+
+enum Overflow {
+    Word(usize),                                // Номер слова, не поместившегося в строку
+    Width(DocError),
+}
+
+fn word_error(tokens: &[&str], sources: &[(usize, &str)], overflow: Overflow) -> DocError {
+    let token: usize = match overflow {
+        Overflow::Word(token) => token,
+        Overflow::Width(error) => return error,
+    };
+    DocError::WordTooLong {
+        word: tokens[token].to_string(),
+        range: token_range(tokens, sources, token),
+        word_index: token,
+    }
+}
+
+fn token_range(tokens: &[&str], sources: &[(usize, &str)], token: usize) -> Range<usize> {   // Байты слова в куске текста, из которого оно взято
+    let word: &str = tokens[token];
+    let source: &str = sources.iter().rev().find(|&&(first_token, _)| first_token <= token).map_or(word, |&(_, source)| source);
+    let start: usize = word.as_ptr() as usize - source.as_ptr() as usize;          // Слова - срезы исходного текста
+    start..start + word.len()
+}
+
+fn glued_gaps(words: &[&str], options: &Options) -> Vec<bool> {                     // Промежутки после слов, на которых строка не обрывается
+    let mut glued: Vec<bool> =
+        (0..words.len())
+            .map(|index| {
+                words.get(index + 1).is_some_and(|next| {
+                    words[index].ends_with(WORD_JOINERS) || next.starts_with(WORD_JOINERS)
+                        || (options.locale == Locale::French && french_gap(words[index], next))
+                        || options.keep_pairs.iter().any(|(first, second)| first.matches(words[index]) && second.matches(next))
+                })
+            })
+            .collect();
+    for phrase in &options.keep_together {
+        let phrase: Vec<&str> = phrase.split(is_breaking_whitespace).filter(|word| !word.is_empty()).collect();
+        if phrase.len() < 2 {
+            continue;
+        }
+        for start in 0..words.len().saturating_sub(phrase.len() - 1) {
+            if words[start..start + phrase.len()] == phrase[..] {
+                glued[start..start + phrase.len() - 1].fill(true);
+            }
+        }
+    }
+    glued
+}
+
+fn avoid_widow(breaks: &mut [Range<usize>], items: &[wrap::Item], line_width: &dyn Fn(usize) -> usize) {   // Одинокое слово последней строки получает соседа из предыдущей
+    let [.., previous, last] = breaks else {
+        return;
+    };
+    let word_ends: Vec<usize> = (previous.start..previous.end - 1).filter(|&item| items[item].whitespace > 0).collect();
+    let single_word: bool = items[last.start..last.end - 1].iter().all(|item| item.whitespace == 0);
+    let (Some(&split), true) = (word_ends.last(), single_word && word_ends.len() >= 2) else {   // Предыдущая строка сохраняет хотя бы два слова
+        return;
+    };
+    if items[split].glue {
+        return;
+    }
+    if wrap::line_length(&items[split + 1..last.end]) <= line_width(breaks.len() - 1) {
+        breaks[breaks.len() - 2].end = split + 1;
+        breaks[breaks.len() - 1].start = split + 1;
+    }
+}
+
+struct Layout<'o> {                                                                 // Всё, что нужно для разбиения блока на строки
+    line_widths: Vec<usize>,                    // Ширины строк подряд; последняя повторяется для всех следующих строк
+    initial_indent: usize,                      // Ширина Options::initial_indent вместе с Options::prefix
+    subsequent_indent: usize,
+    space_width: usize,
+    hyphen_width: usize,
+    narrow_width: usize,                        // Ширина узкого неразрывного пробела Locale::French
+    options: &'o Options,
+}
+
+impl Layout<'_> {
+    fn new(line_widths: Vec<usize>, options: &Options) -> Result<Layout<'_>, DocError> {   // line_widths не пуст
+        let hyphen_width: usize = width::text_width(&options.hyphen, options);
+        let prefix: usize = width::text_width(&options.prefix, options);
+        let initial_indent: usize = prefix.saturating_add(width::text_width(&options.initial_indent, options));
+        let subsequent_indent: usize = prefix.saturating_add(width::text_width(&options.subsequent_indent, options));
+        for &line_width in &line_widths {
+            if line_width == 0 {
+                return Err(DocError::ZeroWidth);
+            }
+            if initial_indent.max(subsequent_indent) >= line_width {
+                return Err(DocError::WidthTooSmall { line_width, minimum: initial_indent.max(subsequent_indent) + 1 });   // После отступа не остаётся места для текста
+            }
+        }
+
+        Ok(Layout {
+            line_widths: line_widths.into_iter().map(|line_width| line_width.min(usize::MAX - 1)).collect(),   // Переполненная (насыщенная) длина строки никогда не помещается
+            initial_indent,
+            subsequent_indent,
+            space_width: width::text_width(" ", options).max(1),
+            hyphen_width,
+            narrow_width: width::text_width(NARROW_NO_BREAK_SPACE, options),
+            options,
+        })
+    }
+    fn width(&self, line: usize) -> usize {                                         // Ширина строки документа с данным номером
+        self.line_widths[line.min(self.line_widths.len() - 1)]
+    }
+    fn narrowest_width(&self) -> usize {
+        self.line_widths.iter().copied().min().unwrap_or(0)
+    }
+    fn line_width(&self, block: &Block, first_line: usize, line: usize) -> usize {  // Место под текст в строке блока с данным номером; first_line - номер первой строки блока
+        let indent: usize = if block.starts_paragraph && line == 0 { self.initial_indent } else { self.subsequent_indent };
+        (self.width(first_line + line) - indent).saturating_sub(block.hanging)
+    }
+    fn narrowest(&self, block: &Block) -> usize {                                   // Куски слов должны помещаться в любую строку блока
+        let indent: usize = if block.starts_paragraph { self.initial_indent.max(self.subsequent_indent) } else { self.subsequent_indent };
+        (self.narrowest_width() - indent).saturating_sub(block.hanging)
+    }
+    fn block<'a>(&self, tokens: &[&'a str], sources: &[(usize, &str)], block: &Block, arena: &mut Vec<&'a str>, lines: &mut Vec<Line>) -> Result<(), Overflow> {
+        let options: &Options = self.options;
+        if block.verbatim {
+            for token in &tokens[block.tokens.clone()] {
+                arena.push(token);
+                lines.push(Line {
+                    words: arena.len() - 1..arena.len(),
+                    char_counter: width::text_width(&expand_tabs(token, options), options),
+                    hyphen: false,
+                    end: LineBreak::Hard,
+                    paragraph_start: false,
+                    list: ListIndent::None,
+                    verbatim: true,
+                    ellipsis: false,
+                    width: self.width(lines.len()),
+                });
+            }
+            if let Some(line) = lines.last_mut().filter(|_| !block.tokens.is_empty()) {
+                line.end = block.end;
+            }
+            return Ok(());
+        }
+        let first_word: usize = block.tokens.start + usize::from(block.hanging > 0);   // Маркер списка пишется отдельно от слов строки
+        let words: &[&str] = &tokens[first_word..block.tokens.end];
+        let first_line: usize = lines.len();
+        let line_width = |line: usize| self.line_width(block, first_line, line);
+        let narrowest: usize = self.narrowest(block);
+        let fragments: Vec<Fragment> =
+            words.iter()
+                .flat_map(|word| Fragment::split_word(word, narrowest, options))
+                .collect();
+
+        if options.overflow == OverflowPolicy::BreakAnywhere && narrowest <= self.hyphen_width && fragments.iter().any(|fragment| fragment.hyphen) {
+            let line_width: usize = self.narrowest_width();
+            return Err(Overflow::Width(DocError::WidthTooSmall { line_width, minimum: line_width - narrowest + self.hyphen_width + 1 }));   // Рядом со знаком переноса не помещается ни один символ
+        }
+
+        let mut glued: vec::IntoIter<bool> = glued_gaps(words, options).into_iter();
+        let items: Vec<wrap::Item> =
+            fragments.iter()
+                .enumerate()
+                .map(|(index, fragment)| {
+                    let narrow: bool = options.locale == Locale::French && fragment.word_end && fragments.get(index + 1).is_some_and(|next| french_gap(fragment.word, next.word));
+                    let sentence_end: bool = options.sentence_spacing && fragment.word_end && ends_sentence(fragment.word) && !narrow;   // Промежуток после предложения на пробел шире, если он не узкий
+                    let preserved: usize = fragments.get(index + 1).filter(|_| fragment.word_end).map_or(0, |next| preserved_spaces(sources, fragment.word, next.word, options));
+                    let item: wrap::Item = fragment.item(self.space_width.saturating_mul(options.min_gap.max(1) + usize::from(sentence_end) + preserved), self.hyphen_width);
+                    wrap::Item {
+                        whitespace: if narrow { self.narrow_width } else { item.whitespace },   // Узкий неразрывный пробел не растягивается
+                        hang:
+                            match (options.hanging_punctuation, fragment.hyphen) {
+                                (false, _) => 0,
+                                (true, true) => hanging_width(&options.hyphen, options).min(item.penalty),
+                                (true, false) => hanging_width(&fragment.word[fragment.range.clone()], options).min(item.width),
+                            },
+                        glue: fragment.word_end && glued.next().unwrap_or(false),  // У каждого слова ровно один последний фрагмент
+                        ..item
+                    }
+                })
+                .collect();
+        let mut breaks: Vec<Range<usize>> = match options.break_algorithm {
+            BreakAlgorithm::Greedy => wrap::greedy(&items, &line_width),
+            BreakAlgorithm::Optimal => wrap::optimal(&items, &line_width),
+            BreakAlgorithm::Balanced => wrap::balanced(&items, &line_width),
+        };
+        let narrow_items: Vec<wrap::Item> = items.iter().map(|item| wrap::Item { whitespace: item.whitespace.min(self.space_width), ..*item }).collect();   // Промежутки, сжатые до одного пробела
+        if let (Alignment::Justify, Some(max_gap)) = (options.alignment, options.max_gap) {
+            self.pull_up(&mut breaks, &items, &narrow_items, max_gap, &line_width);
+        }
+        if options.avoid_widows {
+            avoid_widow(&mut breaks, &items, &line_width);
+        }
+        let bad_line: Option<usize> =
+            if options.overflow == OverflowPolicy::Error {
+                breaks.iter().enumerate().position(|(line, range)| {
+                    let kept_url: bool = options.keep_urls && range.len() == 1 && split::is_url(fragments[range.start].word);   // Целый адрес выходит за ширину строки вместо ошибки
+                    wrap::line_length(&narrow_items[range.clone()]) > line_width(line) && !kept_url
+                })
+            } else {
+                None
+            };
+
+        lines.extend(breaks[..bad_line.unwrap_or(breaks.len())].iter().map(|range| Line::from_fragments(&fragments[range.clone()], self.hyphen_width, arena)));   // При ошибке строки до неё остаются для from_str_partial
+        for (index, line) in lines[first_line..].iter_mut().enumerate() {
+            line.width = self.width(first_line + index);
+        }
+        if let Some(line) = lines.get_mut(first_line) {
+            line.paragraph_start = block.starts_paragraph;
+        }
+        if block.hanging > 0 {
+            for (index, line) in lines[first_line..].iter_mut().enumerate() {
+                line.list = if index == 0 { ListIndent::Marker { token: block.tokens.start, width: block.hanging } } else { ListIndent::Hanging(block.hanging) };
+            }
+        }
+        if let Some(bad_line) = bad_line {
+            let word: &str = fragments[breaks[bad_line].end - 1].word;              // Не помещается только строка из одного фрагмента
+            let index: usize = words.iter().position(|token| core::ptr::eq(*token, word)).unwrap_or(0);
+            return Err(Overflow::Word(first_word + index));
+        }
+        if let Some(line) = lines[first_line..].last_mut() {
+            line.end = block.end;
+        }
+        Ok(())
+    }
+    fn pull_up(&self, breaks: &mut [Range<usize>], items: &[wrap::Item], narrow_items: &[wrap::Item], max_gap: usize, line_width: &dyn Fn(usize) -> usize) {   // Слишком растянутая строка забирает слово из следующей, сжимая промежутки ниже Options::min_gap
+        for line in 0..breaks.len().saturating_sub(1) {
+            let (range, next): (Range<usize>, Range<usize>) = (breaks[line].clone(), breaks[line + 1].clone());
+            let gap_count: usize = items[range.start..range.end - 1].iter().filter(|item| item.whitespace > 0).count();
+            let slack: usize = line_width(line).saturating_sub(wrap::line_length(&items[range.clone()])) / self.space_width;
+            let min_gap: usize = self.options.min_gap.max(1);
+            if next.len() < 2 || gap_count == 0 || items[next.start].glue || min_gap + slack.div_ceil(gap_count) <= max_gap {
+                continue;
+            }
+            if wrap::line_length(&narrow_items[range.start..next.start + 1]) <= line_width(line) {
+                breaks[line].end += 1;
+                breaks[line + 1].start += 1;
+            }
+        }
+    }
+    fn blocks<'a>(&self, tokens: &[&'a str], sources: &[(usize, &str)], blocks: &[Block]) -> Result<LaidOut<'a>, Overflow> {
+        match self.blocks_partial(tokens, sources, blocks) {
+            (laid_out, None) => Ok(laid_out),
+            (_, Some(overflow)) => Err(overflow),
+        }
+    }
+    fn blocks_partial<'a>(&self, tokens: &[&'a str], sources: &[(usize, &str)], blocks: &[Block]) -> (LaidOut<'a>, Option<Overflow>) {   // Строки до первой ошибки и сама ошибка
+        let mut arena: Vec<&str> = Vec::new();
+        let mut lines: Vec<Line> = Vec::new();
+        for block in blocks {                                                       // Каждый блок разбивается на строки независимо
+            if let Err(overflow) = self.block(tokens, sources, block, &mut arena, &mut lines) {
+                return ((arena, lines), Some(overflow));
+            }
+        }
+        ((arena, lines), None)
+    }
+    #[cfg(not(feature = "parallel"))]
+    fn lay_out<'a>(&self, tokens: &[&'a str], sources: &[(usize, &str)], blocks: &[Block]) -> Result<LaidOut<'a>, Overflow> {
+        self.blocks(tokens, sources, blocks)
+    }
+    #[cfg(feature = "parallel")]
+    fn lay_out<'a>(&self, tokens: &[&'a str], sources: &[(usize, &str)], blocks: &[Block]) -> Result<LaidOut<'a>, Overflow> {
+        let thread_count: usize = std::thread::available_parallelism().map_or(1, usize::from).min(blocks.len());
+        if thread_count < 2 || self.line_widths.len() > 1 {                         // Ширина строки зависит от её номера в документе - блоки по порядку
+            return self.blocks(tokens, sources, blocks);
+        }
+
+        let chunk_size: usize = blocks.len().div_ceil(thread_count);
+        let parts: Vec<Result<LaidOut, Overflow>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = blocks.chunks(chunk_size)
+                .map(|chunk| scope.spawn(move || self.blocks(tokens, sources, chunk)))
+                .collect();
+            handles.into_iter().map(|handle| handle.join().expect("поток разбиения абзацев не паникует")).collect()
+        });
+
+        let mut arena: Vec<&str> = Vec::new();
+        let mut lines: Vec<Line> = Vec::new();
+        for part in parts {                                                         // Склеиваем части по порядку, сдвигая диапазоны слов
+            let (part_arena, part_lines): LaidOut = part?;
+            let offset: usize = arena.len();
+            arena.extend(part_arena);
+            lines.extend(part_lines.into_iter().map(|line| Line { words: line.words.start + offset..line.words.end + offset, ..line }));
+        }
+        Ok((arena, lines))
+    }
+}
+
+pub struct Document<'a> {
+    tokens: Vec<&'a str>,                       // Слова входного текста - для повторного разбиения без разбора текста
+    sources: Vec<(usize, &'a str)>,             // Куски текста (from_str, push_text, push_word) и номера их первых слов
+    blocks: Vec<Block>,
+    words: Vec<&'a str>,                        // Слова всех строк подряд, чтобы не выделять память под каждую строку
+    lines: Vec<Line>,
+    line_widths: Vec<usize>,                    // Ширины строк подряд, последняя повторяется; не пуст
+    space_width: usize,                           // Ширина пробела по мере Options::measure - единица, в которой считаются промежутки
+    options: Options,
+    sealed: bool,                               // Последний абзац закрыт: следующий текст начнёт новый
+    continuation: Continuation,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Continuation {                           // Место документа в тексте, который форматируется по абзацам (transform_reader, Editor)
+    first_line: usize,                          // Строк текста выше документа - с них продолжается счёт строк GapStrategy
+    more: bool,                                 // Ниже документа есть текст: Options::max_lines, кончившийся на последней строке, ставит многоточие
+}
+
+impl<'a> Document<'a> {
+    pub fn new(line_width: usize, options: Options) -> Document<'a> {                // Пустой документ для построения через push_text/push_word
+        Document {
+            tokens: Vec::new(),
+            sources: Vec::new(),
+            blocks: Vec::new(),
+            words: Vec::new(),
+            lines: Vec::new(),
+            line_widths: vec![line_width],
+            space_width: width::text_width(" ", &options).max(1),
+            options,
+            sealed: false,
+            continuation: Continuation::default(),
+        }
+    }
+    pub fn from_str(input: &str, line_width: usize) -> Result<Document<'_>, DocError> {
+        Document::from_str_with(input, line_width, Options::default())
+    }
+    pub fn from_str_with(input: &str, line_width: usize, options: Options) -> Result<Document<'_>, DocError> {
+        Document::from_str_with_shape(input, &[line_width], options)
+    }
+    pub fn from_words<I: IntoIterator<Item = &'a str>>(words: I, line_width: usize) -> Result<Document<'a>, DocError> {
+        Document::from_words_with(words, line_width, Options::default())
+    }
+    pub fn from_words_with<I: IntoIterator<Item = &'a str>>(words: I, line_width: usize, options: Options) -> Result<Document<'a>, DocError> {   // Уже разобранные слова одним абзацем; слова не делятся по пробелам, как в push_word
+        let tokens: Vec<&str> = words.into_iter().filter(|word| !word.is_empty()).collect();
+        let sources: Vec<(usize, &str)> = tokens.iter().copied().enumerate().collect();   // Каждое слово - отдельный кусок текста
+        let blocks: Vec<Block> = if tokens.is_empty() { Vec::new() } else { vec![Block { tokens: 0..tokens.len(), end: LineBreak::Paragraph, starts_paragraph: true, hanging: 0, verbatim: false }] };
+        let layout: Layout = Layout::new(vec![line_width], &options)?;
+        let (words, lines): LaidOut = layout.lay_out(&tokens, &sources, &blocks).map_err(|overflow| word_error(&tokens, &sources, overflow))?;
+        let space_width: usize = layout.space_width;
+
+        Ok(Document {
+            tokens,
+            sources,
+            blocks,
+            words,
+            lines,
+            line_widths: vec![line_width],
+            space_width,
+            options,
+            sealed: false,
+            continuation: Continuation::default(),
+        })
+    }
+    pub fn from_measured<I: IntoIterator<Item = (&'a str, usize)>>(words: I, line_width: usize) -> Result<Document<'a>, DocError> {
+        Document::from_measured_with(words, line_width, Options::default())
+    }
+    pub fn from_measured_with<I: IntoIterator<Item = (&'a str, usize)>>(words: I, line_width: usize, options: Options) -> Result<Document<'a>, DocError> {   // Слова с уже посчитанными ширинами (например, после шейпинга); одинаковые слова - одной ширины
+        let words: Vec<(&str, usize)> = words.into_iter().collect();
+        let widths: BTreeMap<String, usize> = words.iter().map(|&(word, width)| (word.to_string(), width)).collect();
+        let measure: Arc<dyn Measure + Send + Sync> = Arc::new(width::Measured::new(widths, options.measure.clone()));
+        Document::from_words_with(words.into_iter().map(|(word, _)| word), line_width, Options { measure, ..options })
+    }
+    pub fn from_str_partial(input: &str, line_width: usize, options: Options) -> (Document<'_>, Option<DocError>) {   // Документ из всего, что удалось разбить до ошибки
+        let tab: Option<DocError> = check_tabs(input, &options).err();
+        let end: usize = match tab {
+            Some(DocError::Tab { offset }) => offset,                               // Текст до табуляции
+            _ => input.len(),
+        };
+        let (tokens, blocks): (Vec<&str>, Vec<Block>) = tokenize(&input[..end], &options);
+        let sources: Vec<(usize, &str)> = vec![(0, input)];
+        let ((words, mut lines), error): (LaidOut, Option<DocError>) =
+            match Layout::new(vec![line_width], &options) {
+                Ok(layout) => {
+                    let (laid_out, overflow): (LaidOut, Option<Overflow>) = layout.blocks_partial(&tokens, &sources, &blocks);
+                    (laid_out, overflow.map(|overflow| word_error(&tokens, &sources, overflow)))
+                }
+                Err(error) => ((Vec::new(), Vec::new()), Some(error)),
+            };
+        let error: Option<DocError> = error.or(tab);
+        if let Some(line) = lines.last_mut().filter(|_| error.is_some()) {
+            line.end = LineBreak::Paragraph;                                        // Оборванный текст не растягиваем до ширины
+        }
+
+        let document: Document = Document {
+            tokens,
+            sources,
+            blocks,
+            words,
+            lines,
+            line_widths: vec![line_width],
+            space_width: width::text_width(" ", &options).max(1),
+            options,
+            sealed: false,
+            continuation: Continuation::default(),
+        };
+        (document, error)
+    }
+    pub fn from_str_with_shape<'i>(input: &'i str, line_widths: &[usize], options: Options) -> Result<Document<'i>, DocError> {   // Ширины строк подряд (треугольник, круг...); последняя повторяется для всех следующих строк
+        if line_widths.is_empty() {
+            return Err(DocError::ZeroWidth);
+        }
+        check_tabs(input, &options)?;
+        let (tokens, blocks): (Vec<&str>, Vec<Block>) = tokenize(input, &options);
+        let layout: Layout = Layout::new(line_widths.to_vec(), &options)?;
+        let sources: Vec<(usize, &str)> = vec![(0, input)];
+        let (words, lines): LaidOut = layout.lay_out(&tokens, &sources, &blocks).map_err(|overflow| word_error(&tokens, &sources, overflow))?;
+        let space_width: usize = layout.space_width;
+
+        Ok(Document {
+            tokens,
+            sources,
+            blocks,
+            words,
+            lines,
+            line_widths: line_widths.to_vec(),
+            space_width,
+            options,
+            sealed: false,
+            continuation: Continuation::default(),
+        })
+    }
+    pub fn from_str_with_widths<F: FnMut(usize) -> usize>(input: &str, mut line_width: F, options: Options) -> Result<Document<'_>, DocError> {   // Ширина каждой строки по её номеру в документе (пустые строки между абзацами не считаются) - для обтекания буквиц и врезок
+        check_tabs(input, &options)?;
+        let (tokens, blocks): (Vec<&str>, Vec<Block>) = tokenize(input, &options);
+        let sources: Vec<(usize, &str)> = vec![(0, input)];
+        let line_limit: usize = input.len().saturating_add(blocks.len()).max(1);   // Строк не больше, чем байтов и пустых блоков
+        let mut line_widths: Vec<usize> = vec![line_width(0)];
+        loop {                                                                      // Разбиваем с известными ширинами, пока строк не станет не больше, чем ширин
+            let layout: Layout = Layout::new(line_widths.clone(), &options)?;
+            let laid_out: Result<LaidOut, Overflow> = layout.lay_out(&tokens, &sources, &blocks);
+            let complete: bool = line_widths.len() >= line_limit || laid_out.as_ref().is_ok_and(|(_, lines)| lines.len() <= line_widths.len());
+            if complete {
+                let (words, lines): LaidOut = laid_out.map_err(|overflow| word_error(&tokens, &sources, overflow))?;
+                line_widths.truncate(lines.len().max(1));
+                return Ok(Document {
+                    tokens,
+                    sources,
+                    blocks,
+                    words,
+                    lines,
+                    line_widths,
+                    space_width: layout.space_width,
+                    options,
+                    sealed: false,
+                    continuation: Continuation::default(),
+                });
+            }
+            let line_count: usize = laid_out.map_or(0, |(_, lines)| lines.len()).max(2 * line_widths.len()).min(line_limit);   // Ошибка могла быть из-за повторённой последней ширины
+            line_widths.extend((line_widths.len()..line_count).map(&mut line_width));
+        }
+    }
+    pub fn push_text(&mut self, text: &'a str) -> Result<(), DocError> {           // Продолжает последний абзац, как если бы куски были разделены пробелом; слово на стыке кусков склеивает DocumentOwned::push_text
+        check_tabs(text, &self.options)?;
+        let (tokens, blocks): (Vec<&str>, Vec<Block>) = tokenize(text, &self.options);
+        self.append(text, tokens, blocks)
+    }
+    pub fn push_word(&mut self, word: &'a str) -> Result<(), DocError> {           // Слово добавляется целиком, даже если содержит пробелы
+        if word.is_empty() {
+            return Ok(());
+        }
+        self.append(word, vec![word], vec![Block { tokens: 0..1, end: LineBreak::Paragraph, starts_paragraph: true, hanging: 0, verbatim: false }])
+    }
+    pub fn end_paragraph(&mut self) {
+        self.sealed = true;
+    }
+    fn append(&mut self, source: &'a str, tokens: Vec<&'a str>, mut blocks: Vec<Block>) -> Result<(), DocError> {
+        if blocks.is_empty() {
+            return Ok(());
+        }
+        let token_count: usize = self.tokens.len();
+        let block_count: usize = self.blocks.len();
+        let last_block: Option<Block> = self.blocks.last().cloned();
+        for block in &mut blocks {
+            block.tokens = block.tokens.start + token_count..block.tokens.end + token_count;
+        }
+
+        let first_block: usize =
+            match self.blocks.last_mut() {
+                Some(last) if !self.sealed => {                                     // Первый новый блок дописывается к последнему открытому
+                    let first: Block = blocks.remove(0);
+                    last.tokens.end = first.tokens.end;
+                    last.end = first.end;
+                    block_count - 1
+                }
+                _ => block_count,
+            };
+        let first_line: usize =
+            match first_block.checked_sub(1) {                                      // Строки блока заканчиваются строкой с разрывом не по ширине
+                Some(previous_block) => self.lines.iter().enumerate()
+                    .filter(|(_, line)| line.end != LineBreak::Wrap)
+                    .nth(previous_block)
+                    .map_or(self.lines.len(), |(index, _)| index + 1),
+                None => 0,
+            };
+        let layout: Layout = Layout::new(self.line_widths[first_line.min(self.line_widths.len() - 1)..].to_vec(), &self.options)?;   // Новые строки начинаются с first_line
+        self.tokens.extend(tokens);
+        self.blocks.extend(blocks);
+        self.sources.push((token_count, source));
+
+        let (words, lines): LaidOut =
+            match layout.lay_out(&self.tokens, &self.sources, &self.blocks[first_block..]) {
+                Ok(laid_out) => laid_out,
+                Err(overflow) => {                                                  // Возвращаем документ в прежнее состояние
+                    let error: DocError = word_error(&self.tokens, &self.sources, overflow);
+                    self.sources.pop();
+                    self.tokens.truncate(token_count);
+                    self.blocks.truncate(block_count);
+                    if let (Some(last), Some(block)) = (self.blocks.last_mut(), last_block) {
+                        *last = block;
+                    }
+                    return Err(error);
+                }
+            };
+
+        let word_offset: usize = self.lines.get(first_line).map_or(self.words.len(), |line| line.words.start);
+        self.lines.truncate(first_line);
+        self.words.truncate(word_offset);
+        self.lines.extend(lines.into_iter().map(|line| Line { words: line.words.start + word_offset..line.words.end + word_offset, ..line }));
+        self.words.extend(words);
+        self.sealed = false;
+        Ok(())
+    }
+    pub fn reflow(&mut self, line_width: usize) -> Result<(), DocError> {             // Переразбивает строки под новую ширину; при ошибке документ не меняется
+        let (words, lines): LaidOut =
+            Layout::new(vec![line_width], &self.options)?
+                .lay_out(&self.tokens, &self.sources, &self.blocks)
+                .map_err(|overflow| word_error(&self.tokens, &self.sources, overflow))?;
+        self.words = words;
+        self.lines = lines;
+        self.line_widths = vec![line_width];
+        Ok(())
+    }
+    pub fn fit_width(&self, max_lines: usize) -> Option<(usize, String)> {           // Наименьшая ширина, при которой выходит не больше max_lines строк, и текст при ней
+        let lay_out = |line_width: usize| -> Option<LaidOut<'a>> {
+            Layout::new(vec![line_width], &self.options).ok()?.lay_out(&self.tokens, &self.sources, &self.blocks).ok()
+                .filter(|(_, lines)| lines.len() <= max_lines)
+        };
+        let indent: usize = [&self.options.initial_indent, &self.options.subsequent_indent].iter()
+            .map(|indent| width::text_width(indent, &self.options))
+            .max().unwrap_or(0);
+        let mut wide: usize = self.tokens.iter()                                    // При такой ширине каждый блок помещается в одну строку
+            .map(|token| width::text_width(token, &self.options).saturating_add(self.space_width))
+            .fold(width::text_width(&self.options.prefix, &self.options).saturating_add(indent).saturating_add(1), usize::saturating_add);
+        lay_out(wide)?;
+        let mut narrow: usize = 1;
+        while narrow < wide {
+            let middle: usize = narrow + (wide - narrow) / 2;
+            if lay_out(middle).is_some() {
+                wide = middle;
+            } else {
+                narrow = middle + 1;
+            }
+        }
+        let (words, lines): LaidOut = lay_out(wide)?;
+        let document: Document = Document {
+            tokens: self.tokens.clone(),
+            sources: self.sources.clone(),
+            blocks: self.blocks.clone(),
+            words,
+            lines,
+            line_widths: vec![wide],
+            space_width: self.space_width,
+            options: self.options.clone(),
+            sealed: self.sealed,
+            continuation: self.continuation,
+        };
+        Some((wide, document.format_to_string()))
+    }
+    pub fn line_count(&self) -> usize {                                             // Строки текста без разделителей абзацев
+        self.shown_lines().len()
+    }
+    pub fn width(&self) -> usize {                                                  // Ширина строки; у from_str_with_shape и from_str_with_widths - первой
+        self.line_widths[0]
+    }
+    pub fn word_count(&self) -> usize {                                             // Слова входного текста вместе с маркерами списков
+        self.tokens.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+    pub fn lines(&self) -> impl Iterator<Item = LineView<'_, 'a>> + '_ {
+        self.lines.iter().map(|line| LineView { line, words: self.line_words(line), fill: self.fill(line) })
+    }
+    fn line_words(&self, line: &Line) -> &[&'a str] {
+        &self.words[line.words.clone()]
+    }
+    fn line_alignment(&self, line: &Line) -> Alignment {
+        match self.options.alignment {
+            Alignment::Justify if line.end != LineBreak::Wrap => self.options.last_line.alignment(),
+            Alignment::Justify if self.options.max_gap.is_some_and(|max_gap| {     // Слишком растянутая строка остаётся невыровненной справа
+                let (_, gap, extra, _): (usize, usize, usize, usize) = self.line_spacing(line, Alignment::Justify);
+                gap + usize::from(extra > 0) > max_gap
+            }) => Alignment::Left,
+            alignment => alignment,
+        }
+    }
+    fn indent(&self, line: &Line) -> &str {
+        if line.paragraph_start { &self.options.initial_indent } else { &self.options.subsequent_indent }
+    }
+    fn text_width(&self, line: &Line) -> usize {                                    // Место под текст строки после префикса и отступов
+        line.width.saturating_sub(width::text_width(&self.options.prefix, &self.options)).saturating_sub(width::text_width(self.indent(line), &self.options)).saturating_sub(line.list.width())
+    }
+    fn list_prefix(&self, line: &Line) -> (&str, usize) {                          // Маркер списка и число пробелов после него
+        match line.list {
+            ListIndent::None => ("", 0),
+            ListIndent::Marker { token, .. } => (self.tokens[token], 1),
+            ListIndent::Hanging(width) => ("", width / self.space_width),
+        }
+    }
+    fn min_gap(&self) -> usize {                                                    // Ширина обычного промежутка в пробелах до растяжения
+        self.options.min_gap.max(1)
+    }
+    fn tight_gap(&self, words: &[&str], gap: usize) -> bool {                      // Промежуток между символами CJK или словами Options::segmenter одного слова: без пробела, пока его не растянут
+        (self.options.cjk || self.options.segmenter.is_some()) && words[gap].as_ptr().wrapping_add(words[gap].len()) == words[gap + 1].as_ptr()   // Куски одного слова идут в тексте подряд
+    }
+    fn sentence_gap(&self, words: &[&str], gap: usize) -> bool {                   // Промежуток после конца предложения при Options::sentence_spacing
+        self.options.sentence_spacing && ends_sentence(words[gap]) && !self.tight_gap(words, gap) && !self.narrow_gap(words, gap)   // Узкий пробел не растягивается
+    }
+    fn preserved_gap(&self, words: &[&str], gap: usize) -> usize {               // Сохранённые пробелы промежутка сверх min_gap при !Options::collapse_whitespace
+        if self.tight_gap(words, gap) || self.narrow_gap(words, gap) { 0 } else { preserved_spaces(&self.sources, words[gap], words[gap + 1], &self.options) }
+    }
+    fn preserved_gaps(&self, line: &Line) -> usize {
+        let words: &[&str] = self.line_words(line);
+        (0..words.len().saturating_sub(1)).map(|gap| self.preserved_gap(words, gap)).sum()
+    }
+    fn narrow_gap(&self, words: &[&str], gap: usize) -> bool {                     // Узкий неразрывный пробел Locale::French: пишется U+202F и не растягивается
+        self.options.locale == Locale::French && french_gap(words[gap], words[gap + 1]) && !self.tight_gap(words, gap)
+    }
+    fn narrow_gaps(&self, line: &Line) -> usize {
+        let words: &[&str] = self.line_words(line);
+        (0..words.len().saturating_sub(1)).filter(|&gap| self.narrow_gap(words, gap)).count()
+    }
+    fn stretched_gaps(&self, line: &Line) -> usize {                               // Промежутки, между которыми делятся пробелы строки
+        line.word_count().saturating_sub(1) - self.narrow_gaps(line)
+    }
+    fn whitespace_count(&self, line: &Line) -> usize {                             // Пробелы строки без узких неразрывных, до правого края с выступающим знаком
+        self.spaces_within(line, self.text_width(line).saturating_add(self.hang(line)))
+    }
+    fn spaces_within(&self, line: &Line, text_width: usize) -> usize {             // Не помещающееся слово (OverflowPolicy::Overflow) выходит за ширину строки
+        let narrow: usize = self.narrow_gaps(line).saturating_mul(width::text_width(NARROW_NO_BREAK_SPACE, &self.options));
+        text_width.saturating_sub(line.char_count()).saturating_sub(narrow) / self.space_width
+    }
+    fn hang(&self, line: &Line) -> usize {                                          // Насколько строка выходит за правый край при Options::hanging_punctuation
+        match (self.options.hanging_punctuation && !line.verbatim && !line.ellipsis, self.line_words(line).last()) {
+            (true, Some(_)) if line.hyphen => hanging_width(&self.options.hyphen, &self.options),
+            (true, Some(word)) => hanging_width(word, &self.options),
+            _ => 0,
+        }
+    }
+    fn sentence_gaps(&self, line: &Line) -> usize {
+        let words: &[&str] = self.line_words(line);
+        (0..words.len().saturating_sub(1)).filter(|&gap| self.sentence_gap(words, gap)).count()
+    }
+    fn tight_gaps(&self, line: &Line) -> usize {
+        let words: &[&str] = self.line_words(line);
+        (0..words.len().saturating_sub(1)).filter(|&gap| self.tight_gap(words, gap)).count()
+    }
+    fn fill(&self, line: &Line) -> f64 {                                            // Доля места под текст, которую строка занимает без растяжения промежутков
+        let text_width: usize = self.text_width(line);
+        let narrow: usize = self.narrow_gaps(line).saturating_mul(width::text_width(NARROW_NO_BREAK_SPACE, &self.options));
+        let natural: usize = line.char_count().saturating_add(narrow).saturating_add(self.natural_spaces(line).saturating_mul(self.space_width));
+        if text_width == 0 { 1.0 } else { (natural as f64 / text_width as f64).min(1.0) }
+    }
+    fn natural_spaces(&self, line: &Line) -> usize {                               // Пробелы строки до растяжения: min_gap в промежутке, ещё один после предложения, сохранённые пробелы
+        (self.stretched_gaps(line) - self.tight_gaps(line)).saturating_mul(self.min_gap()) + self.sentence_gaps(line) + self.preserved_gaps(line)
+    }
+    fn line_spacing(&self, line: &Line, alignment: Alignment) -> (usize, usize, usize, usize) {   // (отступ слева, ширина промежутка, число расширенных промежутков, отступ справа); узкие промежутки CJK уже на min_gap
+        let gap_count: usize = self.stretched_gaps(line);
+        let tight: usize = self.tight_gaps(line);
+        let min_gap: usize = self.min_gap();
+        let whitespace_count: usize = self.whitespace_count(line);
+        let slack: usize = whitespace_count.saturating_sub((gap_count - tight).saturating_mul(min_gap)).saturating_sub(self.preserved_gaps(line));
+        let sentences: usize = self.sentence_gaps(line).min(slack);              // Расширенные промежутки достаются концам предложений первыми
+        let rest: usize = slack - sentences;
+        let unhung: usize = rest.saturating_sub(whitespace_count - self.spaces_within(line, self.text_width(line)));   // Строка не у правого края - выступать нечему
+
+        let (leading, gap, extra, trailing): (usize, usize, usize, usize) =
+            match alignment {
+                Alignment::Justify if gap_count > 0 && self.kashidas(line, alignment) > 0 => (0, min_gap, sentences, 0),   // Растягиваются слова, промежутки остаются одиночными
+                Alignment::Justify if self.letter_spaces(line, alignment) > 0 => (0, self.options.letter_spacing.unwrap_or(1).max(min_gap), sentences, 0),   // Промежутки не шире предела, остальное - между буквами
+                Alignment::Justify if gap_count > 0 && tight > 0 => (0, min_gap + slack / gap_count, slack % gap_count, 0),
+                Alignment::Justify if gap_count > 0 => (0, whitespace_count / gap_count, whitespace_count % gap_count, 0),
+                Alignment::Justify | Alignment::Left => (0, min_gap, sentences, unhung),
+                Alignment::Right => (rest, min_gap, sentences, 0),
+                Alignment::Center => (unhung / 2, min_gap, sentences, unhung - unhung / 2),
+            };
+        (leading, gap, extra, if self.options.pad_short_lines { trailing } else { 0 })
+    }
+    fn kashidas(&self, line: &Line, alignment: Alignment) -> usize {               // Сколько кашид вставить в арабские слова строки вместо лишних пробелов
+        let gap_count: usize = self.stretched_gaps(line);
+        if !self.options.kashida || alignment != Alignment::Justify || gap_count == 0 || !self.line_words(line).iter().any(|word| kashida::join_points(word).next().is_some()) {
+            return 0;
+        }
+        let whitespace_count: usize = self.whitespace_count(line);
+        let kashida_width: usize = width::text_width(kashida::TATWEEL.encode_utf8(&mut [0; 4]), &self.options).max(1);
+        whitespace_count.saturating_sub(gap_count * self.min_gap() + self.sentence_gaps(line)) * self.space_width / kashida_width
+    }
+    fn letter_spaces(&self, line: &Line, alignment: Alignment) -> usize {          // Сколько пробелов разложить между буквами, когда промежутки вышли бы шире Options::letter_spacing
+        let Some(max_gap) = self.options.letter_spacing else {
+            return 0;
+        };
+        if alignment != Alignment::Justify || self.kashidas(line, alignment) > 0 || self.line_words(line).iter().all(|word| letter_points(word) == 0) {
+            return 0;
+        }
+        let gaps: usize = self.stretched_gaps(line).saturating_mul(max_gap.max(self.min_gap())) - self.tight_gaps(line) * self.min_gap() + self.sentence_gaps(line);   // Промежуток не уже min_gap
+        self.whitespace_count(line).saturating_sub(gaps)
+    }
+    fn widened(&self, strategy: GapStrategy, line_number: usize, words: &[&str], gap: usize, extra: usize) -> bool {   // Достаётся ли промежутку лишний пробел от деления
+        if self.narrow_gap(words, gap) {
+            return false;
+        }
+        let stretched: Vec<usize> = (0..words.len() - 1).filter(|&other| !self.narrow_gap(words, other)).collect();
+        let sentences: Vec<usize> = stretched.iter().copied().filter(|&other| self.sentence_gap(words, other)).collect();
+        match sentences.iter().position(|&sentence| sentence == gap) {
+            Some(rank) => rank < extra,
+            None => {                                                               // Остаток - остальным промежуткам по стратегии
+                let index: usize = stretched.iter().filter(|&&other| other < gap && !sentences.contains(&other)).count();
+                self.widened_by(strategy, line_number, index, stretched.len() - sentences.len(), extra.saturating_sub(sentences.len()))
+            }
+        }
+    }
+    fn widened_by(&self, strategy: GapStrategy, line_number: usize, gap: usize, gap_count: usize, extra: usize) -> bool {
+        let gap: usize = if self.options.direction == Direction::Rtl { gap_count - 1 - gap } else { gap };   // Справа налево стратегия отсчитывается от конца строки
+        let line_number: usize = self.continuation.first_line + line_number;
+        let rank = |key: &dyn Fn(usize) -> u64| (0..gap_count).filter(|&other| (key(other), other) < (key(gap), gap)).count();
+        match strategy {
+            GapStrategy::LeftBiased => gap < extra,
+            GapStrategy::RightBiased => gap >= gap_count - extra,
+            GapStrategy::Alternating if line_number.is_multiple_of(2) => gap < extra,
+            GapStrategy::Alternating => gap >= gap_count - extra,
+            GapStrategy::CenterOut => rank(&|gap| (2 * gap).abs_diff(gap_count - 1) as u64) < extra,   // Ближе к середине строки - раньше
+            GapStrategy::SeededRandom(seed) => rank(&|gap| mix(seed ^ mix(line_number as u64) ^ mix(gap as u64).rotate_left(32))) < extra,
+        }
+    }
+    fn gap_spans(&self, line: &Line, mask: &[bool]) -> Vec<Range<usize>> {         // Положения промежутков строки в единицах Options::measure
+        let (leading, gap, _, _): (usize, usize, usize, usize) = self.line_spacing(line, self.line_alignment(line));
+        let words: &[&str] = self.line_words(line);
+        let mut x: usize = [&self.options.prefix, self.indent(line)].iter().map(|text| width::text_width(text, &self.options)).sum::<usize>() + line.list.width() + leading * self.space_width;
+        let mut spans: Vec<Range<usize>> = Vec::with_capacity(words.len());
+        for (number, word) in words.iter().enumerate() {
+            x += width::text_width(word, &self.options);
+            if number + 1 < words.len() && self.narrow_gap(words, number) {
+                let narrow: usize = width::text_width(NARROW_NO_BREAK_SPACE, &self.options);
+                spans.push(x..x + narrow);
+                x += narrow;
+            } else if number + 1 < words.len() {
+                let spaces: usize = gap + usize::from(mask[number]) + self.preserved_gap(words, number) - if self.tight_gap(words, number) { self.min_gap() } else { 0 };
+                spans.push(x..x + spaces * self.space_width);
+                x += spaces * self.space_width;
+            }
+        }
+        spans
+    }
+    fn river_masks(&self, lines: &[Line]) -> Vec<Vec<bool>> {                      // Расширенные промежутки строк, подобранные так, чтобы не совпадать с промежутками строки выше
+        if !self.options.avoid_rivers {
+            return Vec::new();
+        }
+        let overlap = |spans: &[Range<usize>], above: &[Range<usize>]| -> usize {
+            spans.iter().flat_map(|span| above.iter().map(move |other| span.end.min(other.end).saturating_sub(span.start.max(other.start)))).sum()
+        };
+        let mut masks: Vec<Vec<bool>> = Vec::with_capacity(lines.len());
+        let mut above: Vec<Range<usize>> = Vec::new();
+        for (line_number, line) in lines.iter().enumerate() {
+            let gap_count: usize = line.word_count().saturating_sub(1);
+            if line.verbatim || gap_count == 0 {
+                masks.push(Vec::new());
+                above.clear();
+                continue;
+            }
+            let (_, _, extra, _): (usize, usize, usize, usize) = self.line_spacing(line, self.line_alignment(line));
+            let seed: u64 = (self.continuation.first_line + line_number) as u64;
+            let strategies: [GapStrategy; 7] = [
+                self.options.gap_strategy, GapStrategy::LeftBiased, GapStrategy::RightBiased, GapStrategy::CenterOut,
+                GapStrategy::SeededRandom(seed), GapStrategy::SeededRandom(!seed), GapStrategy::SeededRandom(seed ^ 0x5555),
+            ];
+            let candidates: usize = if extra > 0 && gap_count > 1 { strategies.len() } else { 1 };
+            let (mask, spans): (Vec<bool>, Vec<Range<usize>>) = strategies[..candidates].iter()
+                .map(|&strategy| {
+                    let mask: Vec<bool> = (0..gap_count).map(|gap| self.widened(strategy, line_number, self.line_words(line), gap, extra)).collect();
+                    let spans: Vec<Range<usize>> = self.gap_spans(line, &mask);
+                    (mask, spans)
+                })
+                .min_by_key(|(_, spans)| overlap(spans, &above))                    // При равенстве остаётся выбранная стратегия
+                .unwrap_or_default();
+            masks.push(mask);
+            above = if line.end == LineBreak::Paragraph { Vec::new() } else { spans };
+        }
+        masks
+    }
+    fn write_line<W: fmt::Write>(&self, line_number: usize, line: &Line, mask: &[bool], out: &mut W) -> fmt::Result {   // mask - расширенные промежутки из river_masks или пустой
+        self.line_pieces(line_number, line, mask, &mut |piece| match piece {
+            Piece::Blank(text) | Piece::Text(text) => out.write_str(text),
+            Piece::Spaces(count, fill) => write_whitespaces(out, count, fill),
+            Piece::Word(_, shown) => write_visible(out, shown, &self.options),
+        })
+    }
+    fn line_pieces<'s>(&'s self, line_number: usize, line: &Line, mask: &[bool], emit: &mut dyn FnMut(Piece<'s, '_>) -> fmt::Result) -> fmt::Result {   // Строка вывода по кускам слева направо
+        if let (true, [word]) = (line.verbatim, self.line_words(line)) {
+            emit(Piece::Blank(separator_prefix(word, &self.options)))?;
+            return emit(Piece::Word(word, &expand_tabs(word, &self.options)));
+        }
+        let (leading_whitespace, base_witespace_width, extra_witespace, trailing_whitespace): (usize,usize,usize,usize) = self.line_spacing(line, self.line_alignment(line));
+        let gap_count: usize = line.word_count().saturating_sub(1);
+
+        emit(Piece::Blank(&self.options.prefix))?;
+        emit(Piece::Blank(self.indent(line)))?;
+        let (marker, marker_spaces): (&str, usize) = self.list_prefix(line);
+        if !marker.is_empty() {
+            emit(Piece::Text(marker))?;
+        }
+        emit(Piece::Spaces(marker_spaces, ' '))?;
+        emit(Piece::Spaces(leading_whitespace, self.options.fill))?;
+        let words: &[&str] = self.line_words(line);
+        let bidi: Option<(Vec<u8>, Vec<usize>)> = self.options.bidi.then(|| {     // Слова переставляются для вывода, строки разбиты в логическом порядке
+            let levels: Vec<u8> = bidi::levels(words, self.options.direction);
+            let order: Vec<usize> = bidi::visual_order(&levels);
+            (levels, order)
+        });
+        let kashidas: usize = self.kashidas(line, self.line_alignment(line));
+        let letter_spaces: usize = self.letter_spaces(line, self.line_alignment(line));
+        let point_count: usize =
+            if letter_spaces > 0 {
+                words.iter().map(|word| letter_points(word)).sum()
+            } else {
+                words.iter().map(|word| kashida::join_points(word).count()).sum()
+            };
+        let mut first_point: usize = 0;
+        for word_number in 0..words.len() {
+            let index: usize = bidi.as_ref().map_or(word_number, |(_, order)| order[word_number]);
+            let mut word: Cow<str> = Cow::Borrowed(words[index]);
+            if kashidas > 0 {
+                word = Cow::Owned(kashida::stretched(&word, first_point, kashidas, point_count));
+                first_point += kashida::join_points(words[index]).count();
+            }
+            if letter_spaces > 0 {
+                word = Cow::Owned(letter_spaced(&word, first_point, letter_spaces, point_count, self.options.fill));
+                first_point += letter_points(words[index]);
+            }
+            if bidi.as_ref().is_some_and(|(levels, _)| levels[index] % 2 == 1) {
+                word = Cow::Owned(bidi::reversed(&word));
+            }
+            emit(Piece::Word(words[index], &word))?;
+
+            if word_number < gap_count && self.narrow_gap(words, word_number) {
+                emit(Piece::Blank(NARROW_NO_BREAK_SPACE))?;
+            } else if word_number < gap_count {
+                let widened: bool = mask.get(word_number).copied().unwrap_or_else(|| self.widened(self.options.gap_strategy, line_number, words, word_number, extra_witespace));
+                let whitespaces: usize =
+                    if widened {
+                        base_witespace_width + 1
+                    } else {
+                        base_witespace_width
+                    } + self.preserved_gap(words, word_number) - if self.tight_gap(words, word_number) { self.min_gap() } else { 0 };
+                emit(Piece::Spaces(whitespaces, self.options.fill))?;
+            }
+        }
+        if line.hyphen && !self.options.hyphen.is_empty() {
+            emit(Piece::Text(&self.options.hyphen))?;
+        }
+        if line.ellipsis {
+            emit(Piece::Text(&self.options.ellipsis))?;
+        }
+        emit(Piece::Spaces(trailing_whitespace, self.options.fill))
+    }
+    fn shown_lines(&self) -> Cow<'_, [Line]> {                                    // Строки вывода: не больше Options::max_lines, последняя - с многоточием
+        let max_lines: usize = match self.options.max_lines {
+            Some(max_lines) if max_lines < self.lines.len() || (self.continuation.more && max_lines == self.lines.len() && max_lines > 0) => max_lines,
+            _ => return Cow::Borrowed(&self.lines),
+        };
+        let mut lines: Vec<Line> = self.lines[..max_lines].to_vec();
+        if let Some(line) = lines.last_mut() {
+            let ellipsis: usize = width::text_width(&self.options.ellipsis, &self.options);
+            let words: &[&str] = self.line_words(line);
+            let mut widths: Vec<usize> = words.iter().map(|word| width::text_width(word, &self.options)).collect();
+            let available: usize = self.text_width(line).saturating_sub(ellipsis);
+            while !widths.is_empty() && widths.iter().fold(self.space_width * self.min_gap() * (widths.len() - 1), |sum, width| sum.saturating_add(*width)) > available {
+                widths.pop();                                                       // Убираем слова, пока многоточие не поместится в строку
+            }
+            line.words.end = line.words.start + widths.len();
+            line.char_counter = widths.iter().fold(ellipsis, |sum, width| sum.saturating_add(*width));
+            line.hyphen = false;
+            line.verbatim = false;
+            line.ellipsis = true;
+            line.end = LineBreak::Paragraph;
+        }
+        Cow::Owned(lines)
+    }
+    fn write_text<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        let lines: Cow<[Line]> = self.shown_lines();
+        let masks: Vec<Vec<bool>> = self.river_masks(&lines);
+        for (line_number, line) in lines.iter().enumerate() {
+            self.write_line(line_number, line, masks.get(line_number).map_or(&[], Vec::as_slice), out)?;
+            if line_number < lines.len() - 1 {
+                if line.end == LineBreak::Paragraph {
+                    write_paragraph_break(out, &self.options)?;
+                } else {
+                    out.write_str(self.options.line_ending.as_str())?;
+                }
+            } else if self.options.ensure_trailing_newline {
+                out.write_str(self.options.line_ending.as_str())?;
+            }
+        }
+        Ok(())
+    }
+    fn line_length(&self, line: &Line) -> usize {                                  // Точная длина строки в байтах, как её запишет write_line
+        if let (true, [word]) = (line.verbatim, self.line_words(line)) {
+            return separator_prefix(word, &self.options).len() + visible_length(&expand_tabs(word, &self.options), &self.options);
+        }
+        let (leading_whitespace, base_witespace_width, extra_witespace, trailing_whitespace): (usize,usize,usize,usize) = self.line_spacing(line, self.line_alignment(line));
+        let gap_count: usize = self.stretched_gaps(line);
+        let whitespace_count: usize = leading_whitespace + gap_count * base_witespace_width + extra_witespace + trailing_whitespace + self.preserved_gaps(line) - self.tight_gaps(line) * self.min_gap();
+        let word_length: usize = self.line_words(line).iter().map(|word| visible_length(word, &self.options)).sum();
+
+        let (marker, marker_spaces): (&str, usize) = self.list_prefix(line);
+        self.options.prefix.len() + self.indent(line).len() + marker.len() + marker_spaces + word_length + whitespace_count * self.options.fill.len_utf8()
+            + if line.hyphen { self.options.hyphen.len() } else { 0 }
+            + self.narrow_gaps(line) * NARROW_NO_BREAK_SPACE.len()
+            + if line.ellipsis { self.options.ellipsis.len() } else { 0 }
+            + self.kashidas(line, self.line_alignment(line)) * kashida::TATWEEL.len_utf8()
+            + self.letter_spaces(line, self.line_alignment(line)) * self.options.fill.len_utf8()
+    }
+    fn text_length(&self) -> usize {
+        let lines: Cow<[Line]> = self.shown_lines();
+        lines.iter().enumerate()
+            .map(|(line_number, line)| {
+                let mut length: usize = self.line_length(line);
+                if line_number < lines.len() - 1 {
+                    length += if line.end == LineBreak::Paragraph { paragraph_break_length(&self.options) } else { self.options.line_ending.as_str().len() };
+                } else if self.options.ensure_trailing_newline {
+                    length += self.options.line_ending.as_str().len();
+                }
+                length
+            })
+            .sum()
+    }
+    pub fn total_badness(&self) -> u64 {                                            // Сумма badness строк, разорванных по ширине; последние строки абзацев и строки Options::markdown не штрафуются. BreakAlgorithm::Optimal минимизирует не её, так что это лишь оценка результата
+        self.lines.iter()
+            .filter(|line| line.end == LineBreak::Wrap && !line.verbatim)
+            .map(|line| {
+                let slack: usize = self.whitespace_count(line).saturating_sub(self.natural_spaces(line));
+                u64::from(badness(slack.saturating_mul(self.space_width), self.stretched_gaps(line), self.space_width))
+            })
+            .sum()
+    }
+    pub fn quality(&self) -> Quality {                                              // Метрики набора, чтобы найти абзацы, которые стоит переписать
+        let gaps: Vec<f64> = self.spaced_words().into_iter()
+            .flat_map(|(_, gaps)| gaps)
+            .map(|gap| f64::from(gap) / self.space_width as f64)
+            .collect();
+        let mean: f64 = gaps.iter().sum::<f64>() / gaps.len().max(1) as f64;
+        let overstretched_lines: usize = self.lines.iter()
+            .filter(|line| !line.verbatim && line.word_count() > 1 && line.char_count() <= self.text_width(line))
+            .filter(|line| {
+                let (_, gap, extra, _): (usize, usize, usize, usize) = self.line_spacing(line, self.line_alignment(line));
+                gap + usize::from(extra > 0) > self.options.stretch_limit
+            })
+            .count();
+        let last_line_fill: f64 = self.shown_lines().last().map_or(0.0, |line| self.fill(line));
+        Quality {
+            max_gap: gaps.iter().fold(0.0, |widest: f64, &gap| widest.max(gap)) as usize,
+            gap_variance: gaps.iter().map(|gap| (gap - mean) * (gap - mean)).sum::<f64>() / gaps.len().max(1) as f64,
+            overstretched_lines,
+            last_line_fill,
+        }
+    }
+    pub fn warnings(&self) -> Vec<Warning> {                                        // Исправимые проблемы, допущенные при разбиении
+        let mut warnings: Vec<Warning> = Vec::new();
+        let layout: Option<Layout> = Layout::new(self.line_widths.clone(), &self.options).ok();
+        for (token, word) in self.tokens.iter().enumerate() {
+            let range: Range<usize> = token_range(&self.tokens, &self.sources, token);
+            warnings.extend(
+                word.char_indices()
+                    .filter(|&(_, c)| is_stripped_control(c, &self.options))
+                    .map(|(index, character)| Warning::ControlStripped { character, offset: range.start + index })
+            );
+            let block: Option<&Block> = self.blocks.get(self.blocks.partition_point(|block| block.tokens.end <= token));
+            let narrowest: usize = layout.as_ref().zip(block).map_or(self.line_widths[0], |(layout, block)| layout.narrowest(block));
+            if self.options.overflow == OverflowPolicy::Truncate && !block.is_some_and(|block| block.verbatim) && width::text_width(word, &self.options) > narrowest {
+                warnings.push(Warning::WordTruncated { word: word.to_string(), range, word_index: token });
+            }
+        }
+
+        for (line_number, line) in self.lines.iter().enumerate() {
+            let (_, base_witespace_width, extra_witespace, _): (usize,usize,usize,usize) = self.line_spacing(line, self.line_alignment(line));
+            let gap: usize = base_witespace_width + usize::from(extra_witespace > 0);
+            if line.verbatim {
+                continue;
+            }
+            if line.char_count() > self.text_width(line) {
+                warnings.push(Warning::LineOverflow { line: line_number });
+            } else if line.word_count() > 1 && gap > self.options.stretch_limit {
+                warnings.push(Warning::LineOverstretched { line: line_number, gap });
+            }
+        }
+        warnings
+    }
+    pub fn format_to_string(&self) -> String {
+        let mut text: String = String::with_capacity(self.text_length());           // Заранее выделяем ровно столько места, сколько займёт текст
+
+        self.write_text(&mut text).expect("запись в String не завершается ошибкой");
+        text
+    }
+    pub fn format_into(&self, out: &mut String) {                                  // Дописывает текст в конец буфера, сохраняя его содержимое и ёмкость
+        out.reserve(self.text_length());
+        self.write_text(out).expect("запись в String не завершается ошибкой");
+    }
+    fn matches(&self, input: &str) -> bool {
+        let mut matcher: Matcher = Matcher { rest: input };
+        self.write_text(&mut matcher).is_ok() && matcher.rest.is_empty()
+    }
+    pub fn format_lines(&self) -> impl Iterator<Item = String> + '_ {               // Строки в том же виде, что и в format_to_string, но по одной
+        self.format_rows().map(|(text, _)| text)
+    }
+    fn format_rows(&self) -> impl Iterator<Item = (String, bool)> + '_ {            // Строки вывода и признак последней строки многострочного абзаца
+        let mut prefix: String = String::new();
+        let shown: Cow<[Line]> = self.shown_lines();
+        let masks: Vec<Vec<bool>> = self.river_masks(&shown);
+        (0..shown.len()).flat_map(move |line_number| {
+            let line: &Line = &shown[line_number];
+            let mut text: String = core::mem::take(&mut prefix);
+            self.write_line(line_number, line, masks.get(line_number).map_or(&[], Vec::as_slice), &mut text).expect("запись в String не завершается ошибкой");
+
+            let mut lines: Vec<(String, bool)> = vec![(text, line.end == LineBreak::Paragraph && !line.paragraph_start)];
+            if line.end == LineBreak::Paragraph && line_number < shown.len() - 1 {
+                let mut pieces: Vec<&str> = self.options.paragraph_separator.split('\n').collect();
+                prefix = pieces.pop().unwrap_or_default().to_string();              // Хвост разделителя без перевода строки начинает следующую строку
+                lines.extend(pieces.into_iter().map(|piece| ([separator_prefix(piece, &self.options), piece].concat(), false)));
+            }
+            lines
+        })
+    }
+    pub fn paginate(&self, lines_per_page: usize) -> impl Iterator<Item = String> + '_ {   // Страницы не больше lines_per_page строк; последняя строка абзаца не начинает страницу
+        let rows: Vec<(String, bool)> = self.format_rows().collect();
+        let lines_per_page: usize = lines_per_page.max(1);
+        let mut start: usize = 0;
+        core::iter::from_fn(move || {
+            if start >= rows.len() {
+                return None;
+            }
+            let mut end: usize = (start + lines_per_page).min(rows.len());
+            if end < rows.len() && rows[end].1 && end - start > 1 {                 // Переносим на следующую страницу и предпоследнюю строку абзаца
+                end -= 1;
+            }
+            let page: Vec<&str> = rows[start..end].iter().map(|(text, _)| text.as_str()).collect();
+            start = end;
+            Some(page.join(self.options.line_ending.as_str()))
+        })
+    }
+    pub fn format_columns(&self, column_count: usize, gutter: &str) -> String {    // Строки по колонкам шириной line_width слева направо, как в газете
+        let lines: Vec<String> = self.format_lines().collect();
+        let column_width: usize = self.line_widths.iter().copied().max().unwrap_or(0);
+        let height: usize = lines.len().div_ceil(column_count.max(1));
+        let mut text: String = String::new();
+        for row in 0..height {
+            if row > 0 {
+                text.push_str(self.options.line_ending.as_str());
+            }
+            let cells: Vec<&String> = lines.iter().skip(row).step_by(height).collect();
+            for (column, cell) in cells.iter().enumerate() {
+                text.push_str(cell);
+                if column < cells.len() - 1 {                                       // Короткие строки добиваются до ширины колонки
+                    let padding: usize = column_width.saturating_sub(width::text_width(cell, &self.options));
+                    text.extend(core::iter::repeat_n(' ', padding));
+                    text.push_str(gutter);
+                }
+            }
+        }
+        text
+    }
+    pub fn layout(&self) -> Vec<Vec<(&str, usize)>> {                              // Слова строк (а также маркеры списков, знаки переноса, многоточие) с отступом от начала строки в единицах Options::measure
+        let lines: Cow<[Line]> = self.shown_lines();
+        let masks: Vec<Vec<bool>> = self.river_masks(&lines);
+        lines.iter().enumerate()
+            .map(|(line_number, line)| {
+                let mut placements: Vec<(&str, usize)> = Vec::with_capacity(line.word_count() + 1);
+                let mut x: usize = 0;
+                let _ = self.line_pieces(line_number, line, masks.get(line_number).map_or(&[], Vec::as_slice), &mut |piece| {
+                    match piece {
+                        Piece::Blank(text) => x = x.saturating_add(width::text_width(text, &self.options)),
+                        Piece::Text(text) => {
+                            placements.push((text, x));
+                            x = x.saturating_add(width::text_width(text, &self.options));
+                        }
+                        Piece::Word(word, shown) => {
+                            placements.push((word, x));
+                            x = x.saturating_add(width::text_width(shown, &self.options));
+                        }
+                        Piece::Spaces(count, _) => x = x.saturating_add(count.saturating_mul(self.space_width)),
+                    }
+                    Ok(())
+                });
+                placements
+            })
+            .collect()
+    }
+    pub fn spaced_words(&self) -> Vec<(Vec<&str>, Vec<u32>)> {                    // Слова строк в порядке вывода и ширины промежутков между ними в единицах Options::measure - для отрисовки своими средствами
+        let lines: Cow<[Line]> = self.shown_lines();
+        let masks: Vec<Vec<bool>> = self.river_masks(&lines);
+        lines.iter().enumerate()
+            .map(|(line_number, line)| {
+                let mut words: Vec<&str> = Vec::with_capacity(line.word_count());
+                let mut gaps: Vec<u32> = Vec::with_capacity(line.word_count().saturating_sub(1));
+                let mut gap: usize = 0;
+                let _ = self.line_pieces(line_number, line, masks.get(line_number).map_or(&[], Vec::as_slice), &mut |piece| {
+                    match piece {
+                        Piece::Word(word, _) => {
+                            if !words.is_empty() {
+                                gaps.push(u32::try_from(gap).unwrap_or(u32::MAX));
+                            }
+                            words.push(word);
+                            gap = 0;
+                        }
+                        Piece::Blank(text) => gap = gap.saturating_add(width::text_width(text, &self.options)),
+                        Piece::Spaces(count, _) => gap = gap.saturating_add(count.saturating_mul(self.space_width)),
+                        Piece::Text(_) => {}
+                    }
+                    Ok(())
+                });
+                (words, gaps)
+            })
+            .collect()
+    }
+    pub fn into_lines(self) -> Vec<String> {
+        self.format_lines().collect()
+    }
+    pub fn format_to_fmt_writer<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        self.write_text(w)
+    }
+    #[cfg(feature = "std")]
+    pub fn format_to_writer<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        let mut adapter: IoAdapter<W> = IoAdapter { inner: w, error: None };
+        match self.write_text(&mut adapter) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(adapter.error.unwrap_or_else(|| io::Error::other("formatter error"))),
+        }
+    }
+}
+
+impl fmt::Display for Document<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_text(f)
+    }
+}
+
+
+pub fn transform(input: &str, line_width: usize) -> Result<String, DocError> {
+    transform_with(input, line_width, Options::default())
+}
+
+pub fn transform_with(input: &str, line_width: usize, options: Options) -> Result<String, DocError> {
+    Document::from_str_with(input, line_width, options)
+        .map(|document|
+            document.format_to_string()
+        )
+}
+
+pub fn transform_lenient(input: &str, line_width: usize, options: Options) -> Result<(String, Vec<Warning>), DocError> {   // Слишком длинные слова обрезаются, управляющие символы удаляются - с предупреждениями вместо ошибки
+    let overflow: OverflowPolicy = if options.overflow == OverflowPolicy::Error { OverflowPolicy::Truncate } else { options.overflow };
+    let options: Options = Options { overflow, strip_control: true, ..options };
+    let document: Document = Document::from_str_with(input, line_width, options)?;
+    Ok((document.format_to_string(), document.warnings()))
+}
+
+pub fn transform_comment(input: &str, line_width: usize, leader: &str, options: Options) -> Result<String, DocError> {   // Блок комментариев ("// ", "# ", " * "...) с отступом первой строки; ширина - вместе с отступом и началом комментария
+    let first_line: &str = input.lines().find(|line| !line.trim().is_empty()).unwrap_or("");
+    let leading: usize = first_line.len() - first_line.trim_start().len();
+    let indent: usize = leading.saturating_sub(leader.len() - leader.trim_start().len());   // Пробелы в начале leader (" * ") принадлежат ему, а не отступу
+    let prefix: String = [&first_line[..indent], leader].concat();
+    transform_with(input, line_width, Options { prefix, ..options })
+}
+
+pub fn transform_table(input: &str, delimiter: char, column_widths: &[usize], gutter: &str, options: Options) -> Result<String, DocError> {   // Строки ввода - ряды таблицы, ячейки выравниваются по ширинам своих колонок
+    let mut rows: Vec<String> = Vec::new();
+    let mut word_count: usize = 0;
+    for (number, row) in input.lines().enumerate() {
+        let cells_count: usize = row.split(delimiter).count();
+        if cells_count > column_widths.len() {                                      // Короткий ряд допустим: недостающие ячейки пусты
+            return Err(DocError::TooManyCells { row: number, cells: cells_count, columns: column_widths.len() });
+        }
+        let mut cells: Vec<Vec<String>> = Vec::new();
+        for (column, cell) in row.split(delimiter).enumerate() {
+            let bytes: usize = cell.as_ptr() as usize - input.as_ptr() as usize;
+            let document: Document = Document::from_str_with(cell, column_widths[column], options.clone())
+                .map_err(|error| error.shifted(bytes, word_count))?;
+            word_count += document.tokens.len();
+            cells.push(document.into_lines());
+        }
+        let height: usize = cells.iter().map(Vec::len).max().unwrap_or(0).max(1);
+        for line in 0..height {
+            let mut text: String = String::new();
+            for (column, lines) in cells.iter().enumerate() {
+                let cell: &str = lines.get(line).map_or("", String::as_str);
+                text.push_str(cell);
+                if column < cells.len() - 1 {                                       // Колонки, кроме последней, добиваются до своей ширины
+                    let padding: usize = column_widths[column].saturating_sub(width::text_width(cell, &options));
+                    text.extend(core::iter::repeat_n(' ', padding));
+                    text.push_str(gutter);
+                }
+            }
+            rows.push(text);
+        }
+    }
+    Ok(rows.join(options.line_ending.as_str()))
+}
+
+pub fn transform_cow(input: &str, line_width: usize) -> Result<Cow<'_, str>, DocError> {
+    transform_cow_with(input, line_width, Options::default())
+}
+
+pub fn transform_cow_with(input: &str, line_width: usize, options: Options) -> Result<Cow<'_, str>, DocError> {   // Уже отформатированный текст возвращается без копирования
+    Document::from_str_with(input, line_width, options)
+        .map(|document|
+            if document.matches(input) {
+                Cow::Borrowed(input)
+            } else {
+                Cow::Owned(document.format_to_string())
+            }
+        )
+}
+
+pub fn unwrap_text(input: &str, dehyphenate: bool) -> String {                       // Обратное преобразование: абзацы в одну строку с одиночными пробелами
+    let mut text: String = String::with_capacity(input.len());
+    for paragraph in paragraphs(input, &Options::default()) {
+        if !text.is_empty() {
+            text.push_str("\n\n");
+        }
+        let mut joined: bool = true;                                                // Следующее слово приклеивается без пробела
+        for line in paragraph.lines() {
+            let mut words = line.split(is_breaking_whitespace).filter(|word| !word.is_empty()).peekable();   // Неразрывные пробелы остаются внутри слов
+            while let Some(word) = words.next() {
+                if !joined {
+                    text.push(' ');
+                }
+                let stem: Option<&str> = word.strip_suffix('-').filter(|stem| stem.chars().next_back().is_some_and(char::is_alphabetic));
+                let hyphenated: bool = dehyphenate && words.peek().is_none() && stem.is_some();   // Перенос в конце строки между буквами
+                let compound: bool = stem.is_some_and(|stem| stem.contains('-'));  // В составном слове (state-of-the-) перенос по своему дефису - его оставляем
+                text.push_str(if hyphenated && !compound { &word[..word.len() - 1] } else { word });
+                joined = hyphenated;
+            }
+        }
+    }
+    text
+}
+
+#[cfg(feature = "std")]
+pub fn transform_reader<R: io::BufRead, W: io::Write>(mut reader: R, mut writer: W, line_width: usize, options: Options) -> Result<(), StreamError> {
+    let mut paragraph: String = String::new();                                      // В памяти держим не больше одного абзаца (NewlinePolicy::Collapse - весь текст)
+    let mut line: String = String::new();
+    let mut first: bool = true;
+    let trailing_newline: bool = options.ensure_trailing_newline;
+    let options: Options = Options { ensure_trailing_newline: false, ..options };  // Перевод строки пишем один раз в конце потока, а не после каждого абзаца
+    let mut paragraph_break: String = String::new();
+    write_paragraph_break(&mut paragraph_break, &options).expect("запись в String не завершается ошибкой");
+    let (mut offset, mut paragraph_start, mut word_count): (usize, usize, usize) = (0, 0, 0);   // Для положения ошибки во всём потоке
+    let mut shown: usize = 0;                                                       // Строк текста уже выведено - от них считается остаток Options::max_lines
+    let mut held: Option<(String, usize)> = None;                                   // Абзац, которым кончился Options::max_lines, и его строки: многоточие зависит от текста после него
+    let write = |writer: &mut W, document: &mut Document, first: &mut bool, first_line: usize| -> io::Result<()> {
+        document.continuation.first_line = first_line;                              // Счёт строк GapStrategy - по всему потоку, как в transform
+        if !*first {
+            writer.write_all(paragraph_break.as_bytes())?;
+        }
+        *first = false;
+        document.format_to_writer(writer)
+    };
+
+    let mut fence: markdown::Fence = markdown::Fence::default();
+    loop {
+        line.clear();
+        let end: bool = reader.read_line(&mut line)? == 0;
+        let fenced: bool = options.markdown && fence.line(line_body(&line, &options));
+        if !end && (options.newlines == NewlinePolicy::Collapse || fenced || !is_blank(&line, &options)) {
+            if paragraph.is_empty() {
+                paragraph_start = offset;
+            }
+            paragraph.push_str(&line);
+            offset += line.len();
+            continue;
+        }
+        offset += line.len();
+
+        if !paragraph.lines().all(|line| is_blank(line, &options)) {
+            let mut document: Document =
+                Document::from_str_with(&paragraph, line_width, Options { max_lines: options.max_lines.map(|max_lines| max_lines.saturating_sub(shown)), ..options.clone() })
+                    .map_err(|error| error.shifted(paragraph_start, word_count))?;
+            word_count += document.tokens.len();
+            if let Some((held, count)) = held.take() {                                // За абзацем, которым кончился Options::max_lines, есть текст
+                let mut document: Document = Document::from_str_with(&held, line_width, Options { max_lines: Some(count), ..options.clone() })?;
+                document.continuation.more = true;
+                write(&mut writer, &mut document, &mut first, shown - count)?;
+            }
+            match options.max_lines {
+                Some(max_lines) if shown >= max_lines => {}                         // Строки кончились: абзац только проверяем на ошибки
+                Some(max_lines) if document.lines.len() == max_lines - shown => {
+                    let count: usize = document.lines.len();
+                    held = Some((core::mem::take(&mut paragraph), count));
+                    shown = max_lines;
+                }
+                _ => {
+                    write(&mut writer, &mut document, &mut first, shown)?;
+                    shown += document.shown_lines().len();
+                }
+            }
+        }
+        paragraph.clear();
+        if end {
+            if let Some((held, count)) = held.take() {
+                write(&mut writer, &mut Document::from_str_with(&held, line_width, Options { max_lines: Some(count), ..options.clone() })?, &mut first, shown - count)?;
+            }
+            if trailing_newline && !first {
+                writer.write_all(options.line_ending.as_str().as_bytes())?;
+            }
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{Alignment, BreakAlgorithm, Direction, DocError, Document, DocumentOwned, Editor, Formatter, GapStrategy, LastLineMode, LineEnding, Measure, NewlinePolicy, Options, OverflowPolicy, Quality, Segmenter, TabPolicy, Warning, WidthMode, WordPattern};
+    use crate::{badness, verify, wrap_f32, Violation, CharSplitter, HyphenSplitter, Locale, Tokenizer, UrlSplitter, WhitespaceTokenizer, WordSplitter};
+
+    use std::borrow::Cow;
+
+    use super::{transform, transform_comment, transform_cow, transform_lenient, transform_with};
+    use core::ops::Range;
+    #[cfg(feature = "std")]
+    use super::{transform_reader, StreamError};
+
+    fn word_too_long(word: &str, range: Range<usize>, word_index: usize) -> DocError {
+        DocError::WordTooLong { word: word.to_string(), range, word_index }
+    }
+
+    #[test]
+    fn simple() {
+        let test_cases = [
+            ("", 5, ""),
+            ("test", 5, "test "),
+            ("Lorem ipsum dolor sit amet consectetur adipiscing elit sed do eiusmod tempor incididunt ut labore et dolore magna aliqua", 12,
+             "Lorem  ipsum\ndolor    sit\namet        \nconsectetur \nadipiscing  \nelit  sed do\neiusmod     \ntempor      \nincididunt  \nut labore et\ndolore magna\naliqua      "),
+            ("Lorem     ipsum    dolor", 17, "Lorem ipsum dolor"),
+        ];
+
+        for &(input, line_width, expected) in &test_cases {
+            println!("input: '{}'", input);
+            assert_eq!(transform(input, line_width), Ok(expected.to_string()));
+        }
+        assert_eq!(transform("abc_abc_abc_abc_abc", 12), Err(word_too_long("abc_abc_abc_abc_abc", 0..19, 0)));
+        assert_eq!(transform("Lorem ipsum\n\n  dolor abc_abc_abc_abc_abc sit", 12), Err(word_too_long("abc_abc_abc_abc_abc", 21..40, 3)));
+    }
+
+    #[test]
+    fn errors() {
+        let test_cases = [
+            (transform("Lorem ab\u{AD}cdef", 0), DocError::ZeroWidth, "line width must be greater than zero"),
+            (transform("Lorem ipsum_dolor", 5), word_too_long("ipsum_dolor", 6..17, 1), "word \"ipsum_dolor\" (word 2 at bytes 6..17) does not fit into the line width"),
+            (transform_with("ab\u{AD}cd", 1, Options { overflow: OverflowPolicy::BreakAnywhere, ..Options::default() }),
+             DocError::WidthTooSmall { line_width: 1, minimum: 2 }, "line width 1 is smaller than the minimum of 2"),
+            (transform_with("Lorem\tipsum", 12, Options { tabs: TabPolicy::Error, ..Options::default() }),
+             DocError::Tab { offset: 5 }, "tab character at byte 5 is not allowed"),
+            (super::transform_table("a,b\na,b,c", ',', &[6, 4], " ", Options::default()),
+             DocError::TooManyCells { row: 1, cells: 3, columns: 2 }, "row 2 has 3 cells, but only 2 column widths are given"),
+        ];
+
+        for (result, error, message) in test_cases {
+            println!("error: {:?}", error);
+            assert_eq!(result, Err(error.clone()));
+            assert_eq!(error.to_string(), message);
+        }
+        assert_eq!(transform_with("abcd", 1, Options { overflow: OverflowPolicy::BreakAnywhere, ..Options::default() }), Ok("a\nb\nc\nd".to_string()));
+        assert_eq!(Document::new(0, Options::default()).push_text("Lorem"), Err(DocError::ZeroWidth));
+        assert!(core::error::Error::source(&DocError::ZeroWidth).is_none());
+    }
+
+    #[test]
+    fn warnings() {
+        let (output, warnings) = transform_lenient("Lo\u{7}rem abc_abc_abc_abc_abc a b\n\nsit", 12, Options::default()).unwrap();
+        assert_eq!(output, "Lorem       \nabc_abc_abc_\na          b\n\nsit         ");
+        assert_eq!(warnings, vec![
+            Warning::ControlStripped { character: '\u{7}', offset: 2 },
+            Warning::WordTruncated { word: "abc_abc_abc_abc_abc".to_string(), range: 7..26, word_index: 1 },
+            Warning::LineOverstretched { line: 2, gap: 10 },
+        ]);
+        assert_eq!(warnings[2].to_string(), "line 3 is stretched to gaps of 10 spaces");
+
+        let options: Options = Options { overflow: OverflowPolicy::Overflow, ignore_ansi: true, alignment: Alignment::Left, ..Options::default() };
+        let (output, warnings) = transform_lenient("\x1b[1mbold\x1b[0m abcdefghijkl", 8, options).unwrap();
+        assert_eq!(output, "\x1b[1mbold\x1b[0m    \nabcdefghijkl");
+        assert_eq!(warnings, vec![Warning::LineOverflow { line: 1 }]);
+
+        assert_eq!(Document::from_str("a\u{7}b", 5).unwrap().warnings(), vec![]);
+        assert_eq!(transform_lenient("Lorem", 0, Options::default()), Err(DocError::ZeroWidth));
+    }
+
+    #[test]
+    fn partial_output() {
+        let input: &str = "Lorem ipsum dolor\n\nsit amet abc_abc_abc_abc_abc consectetur\n\nelit";
+        let (document, error) = Document::from_str_partial(input, 12, Options::default());
+        assert_eq!(document.format_to_string(), "Lorem  ipsum\ndolor       \n\nsit     amet");
+        assert_eq!(error, Some(word_too_long("abc_abc_abc_abc_abc", 28..47, 5)));
+
+        let (document, error) = Document::from_str_partial("Lorem ipsum dolor", 12, Options::default());
+        assert_eq!((document.format_to_string(), error), (transform("Lorem ipsum dolor", 12).unwrap(), None));
+
+        let (document, error) = Document::from_str_partial("Lorem", 0, Options::default());
+        assert_eq!((document.lines().count(), error), (0, Some(DocError::ZeroWidth)));
+    }
+
+    #[test]
+    fn owned_document() {
+        let buffer: String = String::from("Lorem ipsum dolor sit amet, consectetur adipiscing elit");
+        let mut document: Document = Document::new(12, Options::default());
+        document.push_text(&buffer[..17]).unwrap();
+        document.push_word(&buffer[18..21]).unwrap();
+        document.push_text(&buffer[21..]).unwrap();
+        let expected: String = document.format_to_string();
+
+        let owned: DocumentOwned = DocumentOwned::from(document);
+        drop(buffer);
+        assert_eq!(owned.format_to_string(), expected);
+        assert_eq!(owned.to_string(), expected);
+
+        let mut document: Document = Document::from(&owned);
+        assert_eq!(document.format_to_string(), expected);
+        assert_eq!(document.reflow(4), Err(word_too_long("Lorem", 0..5, 0)));
+        assert_eq!(document.reflow(10), Err(word_too_long("consectetur", 7..18, 5)));
+        document.reflow(20).unwrap();
+        assert_eq!(document.format_to_string(), transform("Lorem ipsum dolor sit amet, consectetur adipiscing elit", 20).unwrap());
+
+        let owned: DocumentOwned = DocumentOwned::from_str_with("Lorem ipsum", 5, Options::default()).unwrap();
+        assert_eq!((owned.line_width(), owned.format_to_string()), (5, String::from("Lorem\nipsum")));
+    }
+
+    #[test]
+    fn alignment() {
+        let input: &str = "Lorem ipsum dolor sit amet consectetur";
+        let test_cases = [
+            (Alignment::Left, "Lorem ipsum \ndolor sit   \namet        \nconsectetur "),
+            (Alignment::Right, " Lorem ipsum\n   dolor sit\n        amet\n consectetur"),
+            (Alignment::Center, "Lorem ipsum \n dolor sit  \n    amet    \nconsectetur "),
+            (Alignment::Justify, "Lorem  ipsum\ndolor    sit\namet        \nconsectetur "),
+        ];
+
+        for &(alignment, expected) in &test_cases {
+            println!("alignment: {:?}", alignment);
+            let options: Options = Options { alignment, ..Options::default() };
+            assert_eq!(transform_with(input, 12, options), Ok(expected.to_string()));
+        }
+    }
+
+    #[test]
+    fn last_line() {
+        let input: &str = "Lorem ipsum dolor sit amet sed do";
+        let test_cases = [
+            (Alignment::Justify, LastLineMode::Justify, "Lorem  ipsum\ndolor    sit\namet  sed do"),
+            (Alignment::Justify, LastLineMode::Left, "Lorem  ipsum\ndolor    sit\namet sed do "),
+            (Alignment::Justify, LastLineMode::Center, "Lorem  ipsum\ndolor    sit\namet sed do "),
+            (Alignment::Justify, LastLineMode::Right, "Lorem  ipsum\ndolor    sit\n amet sed do"),
+            (Alignment::Left, LastLineMode::Right, "Lorem ipsum \ndolor sit   \namet sed do "),
+        ];
+
+        for &(alignment, last_line, expected) in &test_cases {
+            println!("alignment: {:?}, last_line: {:?}", alignment, last_line);
+            let options: Options = Options { alignment, last_line, ..Options::default() };
+            assert_eq!(transform_with(input, 12, options), Ok(expected.to_string()));
+        }
+    }
+
+    #[test]
+    fn huge_widths() {
+        struct Huge;                                            // Каждое слово шириной в треть адресного пространства
+        impl Measure for Huge {
+            fn width(&self, text: &str) -> usize {
+                if text.trim().is_empty() { 1 } else { usize::MAX / 3 }
+            }
+        }
+
+        for break_algorithm in [BreakAlgorithm::Greedy, BreakAlgorithm::Optimal, BreakAlgorithm::Balanced] {
+            println!("break_algorithm: {:?}", break_algorithm);
+            let options: Options = Options { break_algorithm, ..Options::default() };
+            assert_eq!(Document::from_str_with("Lorem ipsum dolor", usize::MAX, options.clone()).unwrap().lines().count(), 1);
+
+            let options: Options = Options { measure: Arc::new(Huge), ..options };
+            let document: Document = Document::from_str_with("Lorem ipsum dolor sit", usize::MAX, options.clone()).unwrap();
+            assert_eq!(document.lines().map(|line| line.words().len()).collect::<Vec<usize>>(), vec![2, 2]);
+            assert_eq!(Document::from_str_with("Lorem ipsum", usize::MAX / 2, options.clone()).unwrap().lines().count(), 2);
+            assert_eq!(transform_with("Lorem", usize::MAX / 4, options), Err(word_too_long("Lorem", 0..5, 0)));
+        }
+    }
+
+    #[test]
+    fn optimal_breaking() {
+        let test_cases = [
+            ("", 5, ""),
+            ("test", 5, "test "),
+            ("aaa bb cc ddddd", 6, "aaa   \nbb  cc\nddddd "),
+            ("Lorem ipsum dolor sit amet consectetur adipiscing elit sed do eiusmod tempor incididunt ut labore et dolore magna aliqua", 12,
+             "Lorem  ipsum\ndolor       \nsit     amet\nconsectetur \nadipiscing  \nelit     sed\ndo   eiusmod\ntempor      \nincididunt  \nut labore et\ndolore magna\naliqua      "),
+        ];
+
+        for &(input, line_width, expected) in &test_cases {
+            println!("input: '{}'", input);
+            let options: Options = Options { break_algorithm: BreakAlgorithm::Optimal, ..Options::default() };
+            assert_eq!(transform_with(input, line_width, options), Ok(expected.to_string()));
+        }
+    }
+
+    #[test]
+    fn balanced_breaking() {
+        let test_cases = [
+            ("", 5, ""),
+            ("Lorem ipsum dolor sit amet", 20, "Lorem ipsum         \ndolor sit amet      "),
+            ("aaa bb cc ddddd", 12, "aaa bb      \ncc ddddd    "),
+        ];
+
+        for &(input, line_width, expected) in &test_cases {
+            println!("input: '{}'", input);
+            let options: Options = Options { alignment: Alignment::Left, break_algorithm: BreakAlgorithm::Balanced, ..Options::default() };
+            assert_eq!(transform_with(input, line_width, options), Ok(expected.to_string()));
+        }
+    }
+
+    #[test]
+    fn unicode_width() {
+        let test_cases = [
+            ("Съешь же ещё этих мягких французских булок", 12, "Съешь же ещё\nэтих  мягких\nфранцузских \nбулок       "),
+            ("café naïve résumé", 11, "café  naïve\nrésumé     "),
+            ("ÀÉÎÕÜ", 5, "ÀÉÎÕÜ"),
+        ];
+
+        for &(input, line_width, expected) in &test_cases {
+            println!("input: '{}'", input);
+            assert_eq!(transform(input, line_width), Ok(expected.to_string()));
+        }
+        assert_eq!(transform("ÀÉÎÕÜ", 4), Err(word_too_long("ÀÉÎÕÜ", 0..10, 0)));
+    }
+
+    #[test]
+    fn east_asian_width() {
+        let test_cases = [
+            (WidthMode::Chars, "你好世界 こんにちは", 10, "你好世界 こんにちは"),
+            (WidthMode::Columns, "你好世界 こんにちは", 10, "你好世界  \nこんにちは"),
+            (WidthMode::Columns, "한국어 text", 12, "한국어  text"),
+            (WidthMode::Columns, "cafe\u{301} ok", 8, "cafe\u{301}  ok"),
+        ];
+
+        for &(width_mode, input, line_width, expected) in &test_cases {
+            println!("input: '{}'", input);
+            let options: Options = Options { measure: Arc::new(width_mode), ..Options::default() };
+            assert_eq!(transform_with(input, line_width, options), Ok(expected.to_string()));
+        }
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn grapheme_width() {
+        let test_cases = [
+            ("\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467} family", 8, "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467} family"),
+            ("e\u{301}te\u{301} de\u{301}ja\u{300}", 9, "e\u{301}te\u{301}  de\u{301}ja\u{300}"),
+            ("\u{1F1F7}\u{1F1FA}\u{1F1EB}\u{1F1F7} flags", 8, "\u{1F1F7}\u{1F1FA}\u{1F1EB}\u{1F1F7} flags"),
+        ];
+
+        for &(input, line_width, expected) in &test_cases {
+            println!("input: '{}'", input);
+            let options: Options = Options { measure: Arc::new(WidthMode::Graphemes), ..Options::default() };
+            assert_eq!(transform_with(input, line_width, options), Ok(expected.to_string()));
+        }
+
+        let options: Options = Options { measure: Arc::new(WidthMode::Graphemes), overflow: OverflowPolicy::Truncate, ..Options::default() };
+        assert_eq!(transform_with("x\u{1F44D}\u{1F3FD}yz", 2, options), Ok("x\u{1F44D}\u{1F3FD}".to_string()));
+    }
+
+    #[test]
+    fn ansi_escapes() {
+        let test_cases = [
+            ("\x1b[31mred\x1b[0m text here", 8, "\x1b[31mred\x1b[0m text\nhere    "),
+            ("\x1b[1;4mbold\x1b[m and \x1b]8;;http://x\x1b\\link\x1b]8;;\x07", 13, "\x1b[1;4mbold\x1b[m and \x1b]8;;http://x\x1b\\link\x1b]8;;\x07"),
+        ];
+
+        for &(input, line_width, expected) in &test_cases {
+            println!("input: '{:?}'", input);
+            let options: Options = Options { ignore_ansi: true, ..Options::default() };
+            assert_eq!(transform_with(input, line_width, options), Ok(expected.to_string()));
+        }
+        assert_eq!(transform("\x1b[31mred\x1b[0m", 8), Err(word_too_long("\x1b[31mred\x1b[0m", 0..12, 0)));
+    }
+
+    #[test]
+    fn custom_measure() {
+        struct Pixels;                                          // Узкие "i" и "l", широкие "m" и "w", пробел - 2 пикселя
+        impl Measure for Pixels {
+            fn width(&self, text: &str) -> usize {
+                text.chars().map(|c| match c { 'i' | 'l' => 1, 'm' | 'w' => 4, ' ' => 2, _ => 3 }).sum()
+            }
+        }
+
+        let test_cases = [
+            ("mmm www ill", 17, "mmm  \nwww ill"),
+            ("lil ill mmm", 18, "lil ill     \nmmm   "),
+        ];
+        for &(input, line_width, expected) in &test_cases {
+            println!("input: '{}'", input);
+            let options: Options = Options { measure: Arc::new(Pixels), alignment: Alignment::Left, ..Options::default() };
+            assert_eq!(transform_with(input, line_width, options), Ok(expected.to_string()));
+        }
+        let options: Options = Options { measure: Arc::new(WidthMode::Bytes), ..Options::default() };
+        assert_eq!(transform_with("ёж", 3, options), Err(word_too_long("ёж", 0..4, 0)));
+    }
+
+    #[test]
+    fn non_breaking_space() {
+        let test_cases = [
+            ("10 km away", 6, "10  km\naway  "),
+            ("10\u{A0}km away", 6, "10\u{A0}km \naway  "),
+            ("a\u{A0}b c", 7, "a\u{A0}b   c"),
+            ("Mr.\u{202F}Smith\u{A0}Jr.", 8, "Err"),
+        ];
+
+        for &(input, line_width, expected) in &test_cases {
+            println!("input: '{}'", input);
+            assert_eq!(transform(input, line_width).unwrap_or_else(|_| "Err".to_string()), expected);
+        }
+    }
+
+    #[cfg(feature = "fast-tokenizer")]
+    #[test]
+    fn fast_tokenizer() {
+        let test_cases = [
+            "",
+            "   ",
+            "Lorem ipsum dolor sit amet, consectetur adipiscing elit",
+            "  leading\tand\r\ntrailing  \x0B\x0C",
+            "Съешь\u{2003}же\u{3000}ещё\u{85}этих мягких",
+            "10\u{A0}km\u{202F}away\u{2007}now",
+            "\x1c\x1funit\x1fseparators stay",
+            "longlonglonglonglonglonglong\u{2028}x",
+        ];
+
+        for input in test_cases {
+            println!("input: '{:?}'", input);
+            let reference: Vec<&str> = input.split(super::is_breaking_whitespace).filter(|word| !word.is_empty()).collect();
+            assert_eq!(super::words(input).collect::<Vec<&str>>(), reference);
+        }
+    }
+
+    #[test]
+    fn paragraphs() {
+        let input: &str = "Lorem ipsum dolor\nsit amet\n\n  \t\n\nconsectetur\n \nadipiscing elit\n";
+        let test_cases = [
+            ("\n", "Lorem  ipsum\ndolor    sit\namet        \n\nconsectetur \n\nadipiscing  \nelit        "),
+            ("", "Lorem  ipsum\ndolor    sit\namet        \nconsectetur \nadipiscing  \nelit        "),
+            ("* * *\n", "Lorem  ipsum\ndolor    sit\namet        \n* * *\nconsectetur \n* * *\nadipiscing  \nelit        "),
+        ];
+
+        for (paragraph_separator, expected) in test_cases {
+            println!("separator: '{}'", paragraph_separator);
+            let options: Options = Options { paragraph_separator: paragraph_separator.to_string(), ..Options::default() };
+            assert_eq!(transform_with(input, 12, options), Ok(expected.to_string()));
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_paragraphs() {
+        let paragraph: &str = "Lorem ipsum dolor sit amet consectetur adipiscing elit";
+        let expected: String = transform(paragraph, 12).unwrap();
+        let input: String = vec![paragraph; 64].join("\n\n");
+
+        assert_eq!(transform(&input, 12), Ok(vec![expected.as_str(); 64].join("\n\n")));
+        assert_eq!(transform(&format!("{}\n\nabc_abc_abc_abc_abc", input), 12), Err(word_too_long("abc_abc_abc_abc_abc", 3584..3603, 512)));
+    }
+
+    #[test]
+    fn newline_policy() {
+        let input: &str = "Roses are red,\nViolets are blue\n\nSugar is sweet";
+        let test_cases = [
+            (NewlinePolicy::Collapse, "Roses are\nred, Violets\nare blue\nSugar is\nsweet"),
+            (NewlinePolicy::Preserve, "Roses are\nred,\nViolets are\nblue\n\nSugar is\nsweet"),
+            (NewlinePolicy::ParagraphOnly, "Roses are\nred, Violets\nare blue\n\nSugar is\nsweet"),
+        ];
+
+        for &(newlines, expected) in &test_cases {
+            println!("newlines: {:?}", newlines);
+            let options: Options = Options { newlines, alignment: Alignment::Left, ..Options::default() };
+            let output: String = transform_with(input, 12, options).unwrap();
+            assert_eq!(output.lines().map(str::trim_end).collect::<Vec<&str>>().join("\n"), expected);
+        }
+    }
+
+    #[test]
+    fn formatter() {
+        let formatter: Formatter =
+            Formatter::new(12)
+                .alignment(Alignment::Justify)
+                .last_line(LastLineMode::Left)
+                .break_algorithm(BreakAlgorithm::Optimal)
+                .overflow(OverflowPolicy::BreakAnywhere);
+
+        assert_eq!(formatter.transform("Lorem ipsum dolor sit amet consectetur_adipiscing"), Ok("Lorem  ipsum\ndolor       \nsit     amet\nconsectetur_\nadipiscing  ".to_string()));
+        assert_eq!(Formatter::new(12).transform("Lorem ipsum dolor"), transform("Lorem ipsum dolor", 12));
+        assert_eq!(Formatter::new(5).width_mode(WidthMode::Bytes).transform("ёжик"), Err(word_too_long("ёжик", 0..8, 0)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn format_to_writer() {
+        let input: &str = "Lorem ipsum dolor sit amet\n\nconsectetur adipiscing elit sed\u{AD}do";
+        let document: Document = Document::from_str(input, 12).unwrap();
+
+        let mut output: Vec<u8> = Vec::new();
+        document.format_to_writer(&mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), document.format_to_string());
+
+        struct Broken;
+        impl std::io::Write for Broken {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "closed"))
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        assert_eq!(document.format_to_writer(&mut Broken).unwrap_err().kind(), std::io::ErrorKind::BrokenPipe);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn streaming() {
+        let test_cases = [
+            "",
+            "\n\n",
+            "Lorem ipsum dolor sit amet\n\n\nconsectetur\n  \nadipiscing elit\n",
+            "Roses are red,\nViolets are blue\n\nSugar is sweet",
+        ];
+
+        for input in test_cases {
+            for newlines in [NewlinePolicy::Collapse, NewlinePolicy::Preserve, NewlinePolicy::ParagraphOnly] {
+                println!("input: '{}', newlines: {:?}", input, newlines);
+                let options: Options = Options { newlines, paragraph_separator: "~\n".to_string(), ..Options::default() };
+                let mut output: Vec<u8> = Vec::new();
+                transform_reader(std::io::BufReader::with_capacity(4, input.as_bytes()), &mut output, 12, options.clone()).unwrap();
+                assert_eq!(String::from_utf8(output).unwrap(), transform_with(input, 12, options).unwrap());
+            }
+        }
+
+        let result = transform_reader("ok\n\nand abc_abc_abc_abc_abc".as_bytes(), std::io::sink(), 12, Options::default());
+        assert!(matches!(result, Err(StreamError::Doc(DocError::WordTooLong { range, word_index: 2, .. })) if range == (8..27)));
+        let result = transform_reader(&[0xFF, 0xFE][..], std::io::sink(), 12, Options::default());
+        assert!(matches!(result, Err(StreamError::Io(_))));
+    }
+
+    #[test]
+    fn incremental_reflow() {
+        let options: Options = Options { paragraph_separator: "* * *\n".to_string(), ..Options::default() };
+        let mut editor: Editor = Editor::new("Lorem ipsum dolor\n\nsit amet\n\nconsectetur", 12, options.clone()).unwrap();
+        let edits = [
+            (6..6, "very ", 0..2),
+            (0..0, "New first\n\n", 0..4),
+            (28..30, "", 1..4),
+            (0..11, "", 0..2),
+            (13..13, "\n\nsplit", 0..4),
+            (0..0, "abc_abc_abc_abc_abc ", 0..0),
+        ];
+
+        for (range, text, changed) in edits {
+            println!("edit: {:?} -> '{}'", range, text);
+            let expected: Result<String, DocError> = transform_with(&format!("{}{}{}", &editor.text()[..range.start], text, &editor.text()[range.end..]), 12, options.clone());
+            let before: Vec<String> = editor.lines().to_vec();
+            let result = editor.replace(range, text);
+            match expected {
+                Ok(expected) => {
+                    assert_eq!(result, Ok(changed));
+                    assert_eq!(editor.lines(), expected.split('\n').collect::<Vec<&str>>());
+                }
+                Err(error) => {
+                    assert_eq!(result, Err(error));
+                    assert_eq!(editor.lines(), before);
+                }
+            }
+        }
+        let text: String = format!("{} abc_abc_abc_abc_abc", editor.text());
+        assert_eq!(editor.insert(editor.text().len(), " abc_abc_abc_abc_abc"), Err(transform_with(&text, 12, options.clone()).unwrap_err()));
+        assert_eq!(editor.delete(0..editor.text().len()), Ok(0..0));
+        assert!(editor.lines().is_empty());
+    }
+
+    #[test]
+    fn incremental_reflow_options() {
+        let test_cases = [
+            Formatter::new(12),
+            Formatter::new(12).max_lines(3),
+            Formatter::new(12).max_lines(5).ellipsis("..."),
+            Formatter::new(12).gap_strategy(GapStrategy::Alternating),
+            Formatter::new(12).gap_strategy(GapStrategy::SeededRandom(5)).avoid_rivers(true),
+            Formatter::new(12).gap_strategy(GapStrategy::Alternating).max_lines(4),
+        ];
+        let edits = [
+            (6..6, "very "),
+            (0..0, "New first\n\n"),
+            (0..11, ""),
+            (31..33, " "),
+            (10..10, "\n\n"),
+            (0..12, ""),
+        ];
+        for formatter in &test_cases {
+            println!("options: {:?}", formatter.options());
+            let mut editor: Editor = Editor::new("Lorem ipsum dolor sit amet\n\nconsectetur adipiscing elit\n\nsed do eiusmod tempor", 12, formatter.options().clone()).unwrap();
+            for (range, text) in edits.iter().cloned() {
+                println!("edit: {:?} -> '{}'", range, text);
+                editor.replace(range, text).unwrap();
+                let document: Document = Document::from_str_with(editor.text(), 12, formatter.options().clone()).unwrap();
+                assert_eq!(editor.lines(), document.format_lines().collect::<Vec<String>>());
+            }
+        }
+    }
+
+    #[test]
+    fn display() {
+        let document: Document = Document::from_str("Lorem ipsum dolor sit amet", 12).unwrap();
+        assert_eq!(format!("[{}]", document), "[Lorem  ipsum\ndolor    sit\namet        ]");
+
+        let mut buffer: String = String::from("> ");
+        document.format_to_fmt_writer(&mut buffer).unwrap();
+        assert_eq!(buffer, format!("> {}", document.format_to_string()));
+
+        let mut frame: String = String::with_capacity(64);
+        for _ in 0..3 {
+            frame.clear();
+            document.format_into(&mut frame);
+            assert_eq!(frame, document.format_to_string());
+        }
+        assert_eq!(frame.capacity(), 64);
+    }
+
+    #[test]
+    fn exact_capacity() {
+        let test_cases = [
+            ("", Options::default()),
+            ("Lorem ipsum dolor sit amet", Options::default()),
+            ("Съешь же ещё этих мягких французских булок", Options { alignment: Alignment::Center, ..Options::default() }),
+            ("extra\u{AD}ordinary words\n\nsit amet", Options { paragraph_separator: "* * *\n".to_string(), ..Options::default() }),
+            ("a abcdefghijkl b", Options { overflow: OverflowPolicy::Overflow, alignment: Alignment::Right, ..Options::default() }),
+            ("Lorem ipsum dolor sit amet", Options { fill: '\u{B7}', alignment: Alignment::Center, ..Options::default() }),
+            ("Lorem ipsum dolor\n\nsit amet", Options { line_ending: LineEnding::CrLf, paragraph_separator: "\n*\n".to_string(), ..Options::default() }),
+            ("Lorem. Ipsum! Dolor sit? Amet", Options { sentence_spacing: true, alignment: Alignment::Center, ..Options::default() }),
+        ];
+
+        for (input, options) in test_cases {
+            println!("input: '{}'", input);
+            let text: String = Document::from_str_with(input, 11, options).unwrap().format_to_string();
+            assert_eq!(text.capacity(), text.len());
+        }
+    }
+
+    #[test]
+    fn exact_capacity_combined() {
+        let inputs: [&str; 3] = [
+            "Quoi ? Il dit : « oui » ! Alors. Bien sûr ; très bien",
+            "foo! ? bar. baz? « qux » quux ! corge",
+            "Lorem ipsum. Dolor sit amet, consectetur adipiscing elit!",
+        ];
+        for input in inputs {
+            for locale in [Locale::Default, Locale::French] {
+                for sentence_spacing in [false, true] {
+                    for alignment in [Alignment::Left, Alignment::Right, Alignment::Center, Alignment::Justify] {
+                        for (letter_spacing, max_gap) in [(None, None), (Some(2), None), (None, Some(2)), (Some(1), Some(3))] {
+                            println!("input: '{}', locale: {:?}, sentence_spacing: {}, alignment: {:?}, letter_spacing: {:?}, max_gap: {:?}", input, locale, sentence_spacing, alignment, letter_spacing, max_gap);
+                            let options: Options = Options { locale, sentence_spacing, alignment, letter_spacing, max_gap, overflow: OverflowPolicy::Overflow, ..Options::default() };
+                            let document: Document = Document::from_str_with(input, 14, options).unwrap();
+                            let text: String = document.format_to_string();
+                            assert_eq!(document.text_length(), text.len());
+                            assert_eq!(text.capacity(), text.len());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fill() {
+        let test_cases = [
+            ("Lorem ipsum dolor", '\u{B7}', Alignment::Justify, "Lorem\u{B7}\u{B7}ipsum\ndolor\u{B7}\u{B7}\u{B7}\u{B7}\u{B7}\u{B7}\u{B7}"),
+            ("Lorem ipsum dolor", '\u{A0}', Alignment::Center, "Lorem\u{A0}ipsum\u{A0}\n\u{A0}\u{A0}\u{A0}dolor\u{A0}\u{A0}\u{A0}\u{A0}"),
+            ("a b", '_', Alignment::Right, "_________a_b"),
+        ];
+        for &(input, fill, alignment, expected) in &test_cases {
+            println!("input: '{}', fill: {:?}", input, fill);
+            let document: Document = Formatter::new(12).fill(fill).alignment(alignment).document(input).unwrap();
+            assert_eq!(document.format_to_string(), expected);
+            assert!(!document.matches(&expected.replace(fill, " ")));
+        }
+    }
+
+    #[test]
+    fn line_endings() {
+        let input: &str = "Lorem ipsum dolor\n\nsit amet";
+        let test_cases = [
+            (LineEnding::Lf, "\n", "Lorem  ipsum\ndolor       \n\nsit     amet"),
+            (LineEnding::CrLf, "\n", "Lorem  ipsum\r\ndolor       \r\n\r\nsit     amet"),
+            (LineEnding::CrLf, "* * *\n", "Lorem  ipsum\r\ndolor       \r\n* * *\r\nsit     amet"),
+            (LineEnding::Custom("<br>"), "--", "Lorem  ipsum<br>dolor       <br>--sit     amet"),
+        ];
+        for &(line_ending, separator, expected) in &test_cases {
+            println!("line_ending: {:?}, separator: '{}'", line_ending, separator);
+            let formatter: Formatter = Formatter::new(12).line_ending(line_ending).paragraph_separator(separator);
+            assert_eq!(formatter.transform(input).unwrap(), expected);
+            if separator == "\n" {                                                  // Иначе разделитель при повторном разборе станет словами
+                assert_eq!(formatter.transform_cow(expected).unwrap(), Cow::Borrowed(expected));
+            }
+            #[cfg(feature = "std")]
+            {
+                let mut output: Vec<u8> = Vec::new();
+                transform_reader(input.as_bytes(), &mut output, 12, formatter.options().clone()).unwrap();
+                assert_eq!(String::from_utf8(output).unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn trailing_newline() {
+        let test_cases = [
+            ("", LineEnding::Lf, ""),
+            ("Lorem ipsum dolor", LineEnding::Lf, "Lorem  ipsum\ndolor       \n"),
+            ("Lorem\n\nipsum", LineEnding::CrLf, "Lorem       \r\n\r\nipsum       \r\n"),
+        ];
+        for &(input, line_ending, expected) in &test_cases {
+            println!("input: '{}'", input);
+            let formatter: Formatter = Formatter::new(12).line_ending(line_ending).ensure_trailing_newline(true);
+            let text: String = formatter.transform(input).unwrap();
+            assert_eq!((text.as_str(), text.capacity()), (expected, expected.len()));
+            assert_eq!(formatter.transform_cow(expected).unwrap(), Cow::Borrowed(expected));
+            #[cfg(feature = "std")]
+            {
+                let mut output: Vec<u8> = Vec::new();
+                transform_reader(input.as_bytes(), &mut output, 12, formatter.options().clone()).unwrap();
+                assert_eq!(String::from_utf8(output).unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn first_line_indent() {
+        let input: &str = "Lorem ipsum dolor sit amet\n\nconsectetur adipiscing elit";
+        let test_cases = [
+            (Formatter::new(12).first_line_indent(3), "Err"),
+            (Formatter::new(12).first_line_indent(3).overflow(OverflowPolicy::Overflow), "   Lorem    \nipsum  dolor\nsit     amet\n\n   consectetur\nadipiscing  \nelit        "),
+            (Formatter::new(14).initial_indent("\u{2014} ").alignment(Alignment::Left), "\u{2014} Lorem ipsum \ndolor sit amet\n\n\u{2014} consectetur \nadipiscing    \nelit          "),
+            (Formatter::new(14).first_line_indent(2).break_algorithm(BreakAlgorithm::Optimal), "  Lorem  ipsum\ndolor sit amet\n\n  consectetur \nadipiscing    \nelit          "),
+        ];
+        for (formatter, expected) in test_cases {
+            println!("initial_indent: '{}'", formatter.options().initial_indent);
+            let text: String = formatter.transform(input).unwrap_or_else(|_| "Err".to_string());
+            assert_eq!(text, expected);
+        }
+
+        assert_eq!(Formatter::new(12).first_line_indent(3).transform("abcdefghij"), Err(word_too_long("abcdefghij", 0..10, 0)));
+        assert_eq!(Formatter::new(12).first_line_indent(3).transform("ab abcdefghij").unwrap(), "   ab       \nabcdefghij  ");
+        assert_eq!(Formatter::new(3).first_line_indent(3).transform("a"), Err(DocError::WidthTooSmall { line_width: 3, minimum: 4 }));
+        assert_eq!(Formatter::new(8).first_line_indent(2).newlines(NewlinePolicy::Preserve).transform("ab cd\nef gh").unwrap(), "  ab  cd\nef    gh");
+        let text: String = Formatter::new(14).first_line_indent(3).transform(input).unwrap();
+        assert_eq!(text.capacity(), text.len());
+    }
+
+    #[test]
+    fn hanging_indent() {
+        let input: &str = "term: a word that is defined here\n\nnext: another one";
+        let test_cases = [
+            (Formatter::new(16).subsequent_indent("    "), "term:   a   word\n    that      is\n    defined here\n\nnext:    another\n    one         "),
+            (Formatter::new(16).initial_indent("* ").subsequent_indent("  ").alignment(Alignment::Left), "* term: a word  \n  that is       \n  defined here  \n\n* next: another \n  one           "),
+            (Formatter::new(16).subsequent_indent("    ").break_algorithm(BreakAlgorithm::Balanced), "term:   a   word\n    that      is\n    defined here\n\nnext:           \n    another  one"),
+        ];
+        for (formatter, expected) in test_cases {
+            println!("initial_indent: '{}', subsequent_indent: '{}'", formatter.options().initial_indent, formatter.options().subsequent_indent);
+            let text: String = formatter.transform(input).unwrap();
+            assert_eq!((text.as_str(), text.capacity()), (expected, expected.len()));
+        }
+        assert_eq!(Formatter::new(4).subsequent_indent("    ").transform("a"), Err(DocError::WidthTooSmall { line_width: 4, minimum: 5 }));
+        assert_eq!(Formatter::new(8).subsequent_indent("   ").transform("abc abcdefgh"), Err(word_too_long("abcdefgh", 4..12, 1)));
+    }
+
+    #[test]
+    fn quoted_prefix() {
+        let input: &str = "> Lorem ipsum dolor sit amet,\n> consectetur\n>\n> adipiscing elit";
+        let test_cases = [
+            (Formatter::new(14), "> Lorem  ipsum\n> dolor    sit\n> amet,       \n> consectetur \n>\n> adipiscing  \n> elit        "),
+            (Formatter::new(14).paragraph_separator("\n-\n"), "> Lorem  ipsum\n> dolor    sit\n> amet,       \n> consectetur \n>\n> -\n> adipiscing  \n> elit        "),
+            (Formatter::new(20).newlines(NewlinePolicy::Preserve).alignment(Alignment::Left), "> Lorem ipsum dolor \n> sit amet,         \n> consectetur       \n>\n> adipiscing elit   "),
+            (Formatter::new(24).newlines(NewlinePolicy::Collapse).first_line_indent(2), ">   Lorem   ipsum  dolor\n> sit  amet, consectetur\n> adipiscing        elit"),
+        ];
+        for (formatter, expected) in test_cases {
+            println!("newlines: {:?}", formatter.options().newlines);
+            let formatter: Formatter = formatter.prefix("> ");
+            let text: String = formatter.transform(input).unwrap();
+            assert_eq!((text.as_str(), text.capacity()), (expected, expected.len()));
+            assert_eq!(formatter.document(input).unwrap().format_lines().collect::<Vec<String>>(), expected.split('\n').collect::<Vec<&str>>());
+            #[cfg(feature = "std")]
+            {
+                let mut output: Vec<u8> = Vec::new();
+                transform_reader(input.as_bytes(), &mut output, formatter.line_width(), formatter.options().clone()).unwrap();
+                assert_eq!(String::from_utf8(output).unwrap(), expected);
+            }
+            let editor: Editor = Editor::new(input, formatter.line_width(), formatter.options().clone()).unwrap();
+            assert_eq!(editor.lines(), expected.split('\n').collect::<Vec<&str>>());
+        }
+
+        assert_eq!(Formatter::new(12).prefix("> ").transform("> Lorem\n> abcdefghijk"), Err(word_too_long("abcdefghijk", 10..21, 1)));
+        assert_eq!(Formatter::new(2).prefix("> ").transform("> a"), Err(DocError::WidthTooSmall { line_width: 2, minimum: 3 }));
+    }
+
+    #[test]
+    fn comments() {
+        let test_cases = [
+            ("// Lorem ipsum dolor sit amet,\n// consectetur adipiscing elit", "// ", 20, "// Lorem ipsum dolor\n// sit         amet,\n// consectetur      \n// adipiscing   elit"),
+            ("    /// Lorem ipsum\n    ///\n    /// dolor sit amet", "/// ", 20, "    /// Lorem  ipsum\n    ///\n    /// dolor    sit\n    /// amet        "),
+            ("  # Lorem ipsum dolor sit amet", "# ", 16, "  # Lorem  ipsum\n  # dolor    sit\n  # amet        "),
+            ("   * Lorem ipsum dolor sit amet\n   *\n   * elit", " * ", 16, "   * Lorem ipsum\n   * dolor   sit\n   * amet       \n   *\n   * elit       "),
+        ];
+        for &(input, leader, line_width, expected) in &test_cases {
+            println!("input: '{}', leader: '{}'", input, leader);
+            let text: String = transform_comment(input, line_width, leader, Options::default()).unwrap();
+            assert!(text.lines().all(|line| line.chars().count() <= line_width));
+            assert_eq!(text, expected);
+        }
+        assert_eq!(transform_comment("    // tiny", 7, "// ", Options::default()), Err(DocError::WidthTooSmall { line_width: 7, minimum: 8 }));
+    }
+
+    #[test]
+    fn list_items() {
+        let input: &str = "Shopping:\n- Lorem ipsum dolor sit\n- amet\n10. consectetur adipiscing elit\n\n* single";
+        let test_cases = [
+            (Formatter::new(16), "Shopping:       \n- Lorem    ipsum\n  dolor      sit\n- amet          \n10. consectetur \n    adipiscing  \n    elit        \n\n* single        "),
+            (Formatter::new(16).alignment(Alignment::Left).newlines(NewlinePolicy::Preserve), "Shopping:       \n- Lorem ipsum   \n  dolor sit     \n- amet          \n10. consectetur \n    adipiscing  \n    elit        \n\n* single        "),
+            (Formatter::new(18).prefix("> ").pad_short_lines(false), "> Shopping:\n> - Lorem    ipsum\n>   dolor      sit\n> - amet\n> 10. consectetur\n>     adipiscing\n>     elit\n>\n> * single"),
+        ];
+        for (formatter, expected) in test_cases {
+            println!("newlines: {:?}, prefix: '{}'", formatter.options().newlines, formatter.options().prefix);
+            let input: String = if formatter.options().prefix.is_empty() { input.to_string() } else { input.lines().map(|line| format!("> {}\n", line)).collect() };
+            let document: Document = formatter.list_items(true).document(&input).unwrap();
+            let text: String = document.format_to_string();
+            assert_eq!((text.as_str(), text.capacity()), (expected, expected.len()));
+        }
+
+        assert_eq!(Formatter::new(16).transform("- Lorem ipsum dolor sit").unwrap(), "-   Lorem  ipsum\ndolor        sit");
+        assert_eq!(Formatter::new(8).list_items(true).transform("1. ab abcdefg"), Err(word_too_long("abcdefg", 6..13, 2)));
+    }
+
+    #[test]
+    fn markdown() {
+        let input: &str = "# Lorem ipsum dolor sit amet\nLorem ipsum dolor sit amet\n\n```rust\nlet  x = 1;\n\n    let y = 2;\n```\n| a | b |\n|---|---|\n\n#hashtag is prose here";
+        let expected: &str = "# Lorem ipsum dolor sit amet\nLorem  ipsum\ndolor    sit\namet        \n\n```rust\nlet  x = 1;\n\n    let y = 2;\n```\n| a | b |\n|---|---|\n\n#hashtag  is\nprose   here";
+        let formatter: Formatter = Formatter::new(12).markdown(true);
+        let document: Document = formatter.document(input).unwrap();
+        let text: String = document.format_to_string();
+        assert_eq!((text.as_str(), text.capacity()), (expected, expected.len()));
+        assert_eq!(document.format_lines().collect::<Vec<String>>(), expected.split('\n').collect::<Vec<&str>>());
+        assert!(document.warnings().is_empty());
+        assert_eq!(Editor::new(input, 12, formatter.options().clone()).unwrap().lines(), expected.split('\n').collect::<Vec<&str>>());
+        #[cfg(feature = "std")]
+        {
+            let mut output: Vec<u8> = Vec::new();
+            transform_reader(std::io::BufReader::with_capacity(4, input.as_bytes()), &mut output, 12, formatter.options().clone()).unwrap();
+            assert_eq!(String::from_utf8(output).unwrap(), expected);
+        }
+
+        let quoted: String = input.lines().map(|line| format!("> {}\n", line)).collect();
+        let expected: String = expected.lines().map(|line| format!("> {}", line).trim_end().to_string()).collect::<Vec<String>>().join("\n");
+        assert_eq!(Formatter::new(14).markdown(true).prefix("> ").pad_short_lines(false).transform(&quoted).unwrap(), expected);
+        assert!(Formatter::new(12).transform(input).unwrap().starts_with("#      Lorem\n"));
+    }
+
+    #[test]
+    fn pad_short_lines() {
+        let test_cases = [
+            ("Lorem ipsum dolor sit amet", Alignment::Justify, LastLineMode::Justify, "Lorem  ipsum\ndolor    sit\namet"),
+            ("Lorem ipsum dolor sit amet", Alignment::Left, LastLineMode::Justify, "Lorem ipsum\ndolor sit\namet"),
+            ("Lorem ipsum dolor\n\nsit amet", Alignment::Justify, LastLineMode::Left, "Lorem  ipsum\ndolor\n\nsit amet"),
+            ("Lorem ipsum dolor", Alignment::Center, LastLineMode::Justify, "Lorem ipsum\n   dolor"),
+            ("Lorem ipsum dolor", Alignment::Right, LastLineMode::Justify, " Lorem ipsum\n       dolor"),
+        ];
+        for &(input, alignment, last_line, expected) in &test_cases {
+            println!("input: '{}', alignment: {:?}", input, alignment);
+            let document: Document = Formatter::new(12).alignment(alignment).last_line(last_line).pad_short_lines(false).document(input).unwrap();
+            let text: String = document.format_to_string();
+            assert_eq!((text.as_str(), text.capacity()), (expected, expected.len()));
+        }
+    }
+
+    #[test]
+    fn max_lines() {
+        let test_cases = [
+            ("Lorem ipsum dolor sit amet", 2, "\u{2026}", LastLineMode::Left, "Lorem  ipsum\ndolor sit\u{2026}  "),
+            ("Lorem ipsum dolor sit amet", 2, "...", LastLineMode::Left, "Lorem  ipsum\ndolor sit..."),
+            ("Lorem ipsum dolor sit amet", 1, "...", LastLineMode::Justify, "Lorem...    "),
+            ("Lorem ipsum dolor sit amet", 1, "\u{2026}", LastLineMode::Justify, "Lorem ipsum\u{2026}"),
+            ("Lorem ipsum dolor\n\nsit amet", 2, "...", LastLineMode::Left, "Lorem  ipsum\ndolor...    "),
+            ("Lorem ipsum dolor sit amet", 3, "...", LastLineMode::Left, "Lorem  ipsum\ndolor    sit\namet        "),
+            ("Lorem ipsum dolor sit amet", 0, "...", LastLineMode::Left, ""),
+            ("Lorem ipsum\n\ndolor sit\n\namet", 2, "...", LastLineMode::Left, "Lorem ipsum \n\ndolor sit..."),
+            ("Lorem ipsum\n\ndolor sit", 2, "...", LastLineMode::Left, "Lorem ipsum \n\ndolor sit   "),
+            ("Lorem ipsum\n\ndolor sit amet\n\nconsectetur", 2, "...", LastLineMode::Left, "Lorem ipsum \n\ndolor sit..."),
+            ("Lorem\n\nipsum\n\ndolor", 1, "...", LastLineMode::Left, "Lorem...    "),
+        ];
+        for &(input, max_lines, ellipsis, last_line, expected) in &test_cases {
+            println!("input: '{}', max_lines: {}, ellipsis: '{}'", input, max_lines, ellipsis);
+            let formatter: Formatter = Formatter::new(12).max_lines(max_lines).ellipsis(ellipsis).last_line(last_line);
+            let document: Document = formatter.document(input).unwrap();
+            let text: String = document.format_to_string();
+            assert_eq!((text.as_str(), text.capacity()), (expected, expected.len()));
+            assert_eq!(document.format_lines().collect::<Vec<String>>().join("\n"), expected);
+            assert_eq!(Editor::new(input, 12, formatter.options().clone()).unwrap().lines().join("\n"), expected);
+            #[cfg(feature = "std")]
+            {
+                let mut output: Vec<u8> = Vec::new();
+                transform_reader(input.as_bytes(), &mut output, 12, formatter.options().clone()).unwrap();
+                assert_eq!(String::from_utf8(output).unwrap(), expected);
+            }
+        }
+        let formatter: Formatter = Formatter::new(9).max_lines(2);
+        let input: &str = "aa b ccc d ee f\n\ngg h iii j kk l\n\nmm n ooo p";
+        let expected: String = formatter.transform(input).unwrap();
+        let mut editor: Editor = Editor::new(input, 9, formatter.options().clone()).unwrap();
+        assert_eq!(editor.lines().join("\n"), expected);
+        editor.delete(0..16).unwrap();                                              // Первый абзац удалён: строки достаются следующим
+        assert_eq!(editor.lines().join("\n"), formatter.transform(editor.text()).unwrap());
+        #[cfg(feature = "std")]
+        {
+            let mut output: Vec<u8> = Vec::new();
+            transform_reader(input.as_bytes(), &mut output, 9, formatter.options().clone()).unwrap();
+            assert_eq!(String::from_utf8(output).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn fit_width() {
+        let test_cases = [
+            ("Lorem ipsum dolor sit amet", 1, Some((26, "Lorem ipsum dolor sit amet"))),
+            ("Lorem ipsum dolor sit amet", 2, Some((14, "Lorem    ipsum\ndolor sit amet"))),
+            ("Lorem ipsum dolor sit amet", 3, Some((11, "Lorem ipsum\ndolor   sit\namet       "))),
+            ("Lorem ipsum\n\ndolor sit amet", 2, Some((14, "Lorem    ipsum\n\ndolor sit amet"))),
+            ("Lorem ipsum\n\ndolor sit amet", 1, None),
+            ("", 0, Some((1, ""))),
+        ];
+        for &(input, max_lines, expected) in &test_cases {
+            println!("input: '{}', max_lines: {}", input, max_lines);
+            let document: Document = Document::from_str(input, 80).unwrap();
+            let fitted: Option<(usize, String)> = document.fit_width(max_lines);
+            assert_eq!(fitted.as_ref().map(|(width, text)| (*width, text.as_str())), expected);
+        }
+    }
+
+    #[test]
+    fn unwrap_text() {
+        let test_cases = [
+            ("Lorem  ipsum\ndolor    sit\namet        ", false, "Lorem ipsum dolor sit amet"),
+            ("Lorem  ipsum\n\n\n  dolor   \nsit amet\n", false, "Lorem ipsum\n\ndolor sit amet"),
+            ("Lorem   ipsum  do-\nlor sit amet", true, "Lorem ipsum dolor sit amet"),
+            ("Lorem   ipsum  do-\nlor sit amet", false, "Lorem ipsum do- lor sit amet"),
+            ("Lorem - ipsum 1-\n2 dolor-sit", true, "Lorem - ipsum 1- 2 dolor-sit"),
+            ("", true, ""),
+            ("aa\u{a0}bb  cc\ndd", false, "aa\u{a0}bb cc dd"),
+            ("10\u{202F}km  \u{2007}x\n y", false, "10\u{202F}km \u{2007}x y"),
+            ("the state-of-the-\nart design", true, "the state-of-the-art design"),
+            ("the state-of-the-\nart design", false, "the state-of-the- art design"),
+        ];
+        for &(input, dehyphenate, expected) in &test_cases {
+            println!("input: '{}', dehyphenate: {}", input, dehyphenate);
+            assert_eq!(super::unwrap_text(input, dehyphenate), expected);
+        }
+        let input: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit\n\nsed do eiusmod";
+        assert_eq!(super::unwrap_text(&transform(input, 16).unwrap(), true), input);
+        let input: &str = "the state-of-the-art design";
+        assert_eq!(super::unwrap_text(&Formatter::new(13).splitter(HyphenSplitter).transform(input).unwrap(), true), input);
+    }
+
+    #[test]
+    fn format_columns() {
+        let input: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit";
+        let test_cases = [
+            (1, " | ", "Lorem ipsum\ndolor   sit\namet,      \nconsectetur\nadipiscing \nelit       "),
+            (2, " | ", "Lorem ipsum | consectetur\ndolor   sit | adipiscing \namet,       | elit       "),
+            (4, "  ", "Lorem ipsum  amet,        adipiscing \ndolor   sit  consectetur  elit       "),
+            (9, "|", "Lorem ipsum|dolor   sit|amet,      |consectetur|adipiscing |elit       "),
+        ];
+        for &(column_count, gutter, expected) in &test_cases {
+            println!("column_count: {}, gutter: '{}'", column_count, gutter);
+            let document: Document = Formatter::new(11).last_line(LastLineMode::Left).document(input).unwrap();
+            assert_eq!(document.format_columns(column_count, gutter), expected);
+        }
+        let document: Document = Formatter::new(8).pad_short_lines(false).document("Lorem ipsum\n\ndolor").unwrap();
+        assert_eq!(document.format_columns(2, "|"), "Lorem   |\nipsum   |dolor");
+        assert_eq!(Document::from_str("", 8).unwrap().format_columns(2, "|"), "");
+    }
+
+    #[test]
+    fn paginate() {
+        let input: &str = "Lorem ipsum dolor sit amet\n\nconsectetur adipiscing elit";
+        let test_cases: [(usize, &[&str]); 5] = [
+            (2, &["Lorem ipsum", "dolor sit\namet", "\nconsectetur", "adipiscing\nelit"]),
+            (3, &["Lorem ipsum\ndolor sit\namet", "\nconsectetur", "adipiscing\nelit"]),
+            (4, &["Lorem ipsum\ndolor sit\namet\n", "consectetur\nadipiscing\nelit"]),
+            (8, &["Lorem ipsum\ndolor sit\namet\n\nconsectetur\nadipiscing\nelit"]),
+            (0, &["Lorem ipsum", "dolor sit", "amet", "", "consectetur", "adipiscing", "elit"]),
+        ];
+        for &(lines_per_page, expected) in &test_cases {
+            println!("lines_per_page: {}", lines_per_page);
+            let document: Document = Formatter::new(11).alignment(Alignment::Left).pad_short_lines(false).document(input).unwrap();
+            assert_eq!(document.paginate(lines_per_page).collect::<Vec<String>>(), expected);
+        }
+        assert_eq!(Document::from_str("", 8).unwrap().paginate(2).count(), 0);
+    }
+
+    #[test]
+    fn transform_table() {
+        let input: &str = "Lorem ipsum dolor\tsit\tamet, consectetur\nadipiscing\t\telit";
+        let test_cases = [
+            ('\t', &[11, 4, 11][..], " | ", Ok("Lorem ipsum | sit  | amet,      \ndolor       |      | consectetur\nadipiscing  |      | elit       ")),
+            ('\t', &[17, 3, 17][..], "  ", Ok("Lorem ipsum dolor  sit  amet, consectetur\nadipiscing              elit             ")),
+            (',', &[30, 12][..], "|", Ok("Lorem  ipsum  dolor  sit  amet|consectetur \nadipiscing                elit")),
+            ('\t', &[11, 4][..], "|", Err(DocError::TooManyCells { row: 0, cells: 3, columns: 2 })),
+            ('\t', &[11, 0, 11][..], "|", Err(DocError::ZeroWidth)),
+            ('\t', &[11, 2, 11][..], "|", Err(DocError::WordTooLong { word: "sit".to_string(), range: 18..21, word_index: 3 })),
+        ];
+        for &(delimiter, column_widths, gutter, ref expected) in &test_cases {
+            println!("delimiter: {:?}, column_widths: {:?}", delimiter, column_widths);
+            assert_eq!(super::transform_table(input, delimiter, column_widths, gutter, Options::default()), expected.clone().map(str::to_string));
+        }
+        assert_eq!(super::transform_table("a,b,c", ',', &[6, 4], "|", Options::default()), Err(DocError::TooManyCells { row: 0, cells: 3, columns: 2 }));
+        assert_eq!(super::transform_table("a,b,c\nd", ',', &[2, 2, 2], "|", Options::default()), Ok("a |b |c \nd ".to_string()));   // Короткий ряд
+    }
+
+    #[test]
+    fn direction() {
+        let test_cases = [
+            ("Lorem ipsum dolor sit amet", 14, Direction::Ltr, "Lorem    ipsum\ndolor sit amet"),
+            ("aa bb cc dd", 15, Direction::Ltr, "aa   bb  cc  dd"),
+            ("aa bb cc dd", 15, Direction::Rtl, "aa  bb  cc   dd"),
+            ("aa bb cc\n\ndd", 11, Direction::Rtl, "aa  bb   cc\n\ndd         "),
+            ("aa bb", 8, Direction::Rtl, "aa    bb"),
+        ];
+        for &(input, line_width, direction, expected) in &test_cases {
+            println!("input: '{}', direction: {:?}", input, direction);
+            assert_eq!(Formatter::new(line_width).direction(direction).transform(input).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn bidi() {
+        let test_cases = [
+            ("Lorem ipsum dolor", 17, Direction::Ltr, "Lorem ipsum dolor"),
+            ("שלום עולם", 9, Direction::Rtl, "םלוע םולש"),
+            ("abc שלום עולם def", 17, Direction::Ltr, "abc םלוע םולש def"),
+            ("שלום abc def עולם", 17, Direction::Rtl, "םלוע abc def םולש"),
+            ("שלום (עולם) 123", 15, Direction::Rtl, "123 (םלוע) םולש"),
+            ("abc שלום, עולם.", 15, Direction::Ltr, "abc .םלוע ,םולש"),
+            ("שלום עולם abc def", 9, Direction::Rtl, "םלוע םולש\nabc def  "),
+        ];
+        for &(input, line_width, direction, expected) in &test_cases {
+            println!("input: '{}', direction: {:?}", input, direction);
+            let text: String = Formatter::new(line_width).direction(direction).bidi(true).last_line(LastLineMode::Left).transform(input).unwrap();
+            assert_eq!(text, expected);
+        }
+    }
+
+    #[test]
+    fn kashida() {
+        let test_cases = [
+            ("سلام عليكم", 12, false, "سلام   عليكم"),
+            ("سلام عليكم", 12, true, "سلام عـليكـم"),
+            ("سلام عليكم", 13, true, "سلـام علـيكـم"),
+            ("سلام عليكم", 14, true, "سلـام عـليـكـم"),
+            ("Lorem ipsum", 13, true, "Lorem   ipsum"),
+            ("دار ورد", 10, true, "دار    ورد"),
+        ];
+        for &(input, line_width, kashida, expected) in &test_cases {
+            println!("input: '{}', line_width: {}, kashida: {}", input, line_width, kashida);
+            let document: Document = Formatter::new(line_width).kashida(kashida).document(input).unwrap();
+            let text: String = document.format_to_string();
+            assert_eq!((text.as_str(), text.capacity()), (expected, expected.len()));
+        }
+    }
+
+    #[test]
+    fn cjk() {
+        let test_cases = [
+            ("日本語の文章です。", 4, Alignment::Justify, "日本語の\n文 章で\nす。  "),
+            ("日本語の文章です。", 6, Alignment::Justify, "日本語の文章\nです。   "),
+            ("日本語の文章 Rustacean", 11, Alignment::Justify, "日 本 語 の 文 章\nRustacean  "),
+            ("日本語の文章 Rust", 8, Alignment::Justify, "日 本 語の文章\nRust    "),
+            ("「日本」語", 3, Alignment::Left, "「日 \n本」語"),
+            ("日本」語", 1, Alignment::Left, "日\n本」\n語"),
+            ("日本 Rust です", 7, Alignment::Justify, "日本 Rust\nです     "),
+            ("日本語の文章", 8, Alignment::Center, " 日本語の文章 "),
+        ];
+        for &(input, line_width, alignment, expected) in &test_cases {
+            println!("input: '{}', line_width: {}, alignment: {:?}", input, line_width, alignment);
+            let document: Document = Formatter::new(line_width).cjk(true).alignment(alignment).last_line(LastLineMode::Left).overflow(OverflowPolicy::Overflow).document(input).unwrap();
+            let text: String = document.format_to_string();
+            assert_eq!((text.as_str(), text.capacity()), (expected, expected.len()));
+        }
+        assert!(matches!(transform("日本語の文章です。", 4), Err(DocError::WordTooLong { .. })));
+    }
+
+    #[test]
+    fn segmenter() {
+        struct Syllables;
+        impl Segmenter for Syllables {
+            fn boundaries(&self, text: &str) -> Vec<usize> {                        // После каждой гласной
+                text.match_indices(['a', 'o', 'u']).map(|(index, _)| index + 1).collect()
+            }
+        }
+        let test_cases = [
+            ("tokonoma", 4, "toko\nnoma"),
+            ("tokonoma", 5, "to ko\nno ma"),
+            ("tokonoma mu", 6, "tokono\nma  mu"),
+            ("tokonoma", 8, "tokonoma"),
+        ];
+        for &(input, line_width, expected) in &test_cases {
+            println!("input: '{}', line_width: {}", input, line_width);
+            assert_eq!(Formatter::new(line_width).segmenter(Syllables).transform(input).unwrap(), expected);
+        }
+    }
+
+    #[cfg(feature = "dictionary")]
+    #[test]
+    fn dictionary_segmenter() {
+        use crate::DictionarySegmenter;
+
+        let segmenter: DictionarySegmenter = DictionarySegmenter::new(["ผม", "สวัสดี", "ครับ"]);
+        let test_words = [
+            ("ผมสวัสดีครับ", vec![6, 24]),
+            ("สวัสดี", vec![]),
+            ("xyzครับ", vec![3]),
+            ("ครับxyzผม", vec![12, 15]),
+        ];
+        for (word, expected) in &test_words {
+            println!("word: '{}'", word);
+            assert_eq!(&segmenter.boundaries(word), expected);
+        }
+
+        let test_cases = [
+            ("ผมสวัสดีครับ", 6, "ผมสวัสดี\nครับ   "),
+            ("ผมสวัสดีครับ", 7, "ผม สวัสดี\nครับ    "),
+        ];
+        for &(input, line_width, expected) in &test_cases {
+            println!("input: '{}', line_width: {}", input, line_width);
+            let text: String = Formatter::new(line_width).width_mode(WidthMode::Columns).segmenter(segmenter.clone()).last_line(LastLineMode::Left).transform(input).unwrap();
+            assert_eq!(text, expected);
+        }
+    }
+
+    #[test]
+    fn letter_spacing() {
+        let test_cases = [
+            ("Lorem ipsum dolor", 16, None, "Lorem      ipsum\ndolor           "),
+            ("Lorem ipsum dolor", 16, Some(2), "Lo re m  ip su m\ndolor           "),
+            ("Lorem ipsum dolor", 16, Some(3), "Lor em   ip su m\ndolor           "),
+            ("Lorem ipsum dolor", 14, Some(4), "Lorem    ipsum\ndolor         "),
+            ("Lorem dolor sit", 13, Some(1), "Lore m dolo r\nsit          "),
+            ("Lorem dolor sit", 13, Some(0), "Lore m dolo r\nsit          "),
+        ];
+        for &(input, line_width, letter_spacing, expected) in &test_cases {
+            println!("input: '{}', line_width: {}, letter_spacing: {:?}", input, line_width, letter_spacing);
+            let options: Options = Options { letter_spacing, last_line: LastLineMode::Left, ..Options::default() };
+            let document: Document = Document::from_str_with(input, line_width, options).unwrap();
+            let text: String = document.format_to_string();
+            assert_eq!((text.as_str(), text.capacity()), (expected, expected.len()));
+        }
+    }
+
+    #[test]
+    fn gap_strategy() {
+        let input: &str = "aa bb cc dd ee ff gg hh ii jj kk";
+        let test_cases = [
+            (GapStrategy::LeftBiased, Direction::Ltr, "aa  bb  cc dd ee\nff  gg  hh ii jj\nkk"),
+            (GapStrategy::RightBiased, Direction::Ltr, "aa bb cc  dd  ee\nff gg hh  ii  jj\nkk"),
+            (GapStrategy::LeftBiased, Direction::Rtl, "aa bb cc  dd  ee\nff gg hh  ii  jj\nkk"),
+            (GapStrategy::CenterOut, Direction::Ltr, "aa bb  cc  dd ee\nff gg  hh  ii jj\nkk"),
+            (GapStrategy::Alternating, Direction::Ltr, "aa  bb  cc dd ee\nff gg hh  ii  jj\nkk"),
+        ];
+        for &(gap_strategy, direction, expected) in &test_cases {
+            println!("gap_strategy: {:?}, direction: {:?}", gap_strategy, direction);
+            let formatter: Formatter = Formatter::new(16).gap_strategy(gap_strategy).direction(direction).pad_short_lines(false);
+            assert_eq!(formatter.transform(input).unwrap(), expected);
+        }
+
+        let random = |seed: u64| Formatter::new(16).gap_strategy(GapStrategy::SeededRandom(seed)).document(input).unwrap();
+        assert_eq!(random(7).format_to_string(), random(7).format_to_string());
+        assert!((0..8).any(|seed| random(seed).format_to_string() != random(seed + 1).format_to_string()));
+        for seed in 0..8 {
+            let document: Document = random(seed);
+            let text: String = document.format_to_string();
+            assert_eq!(text.capacity(), text.len());
+            assert!(text.lines().take(2).all(|line| line.len() == 16 && !line.contains("   ")));
+        }
+    }
+
+    #[test]
+    fn gap_strategy_paragraphs() {
+        let input: &str = "aa bb cc dd ee ff gg hh ii jj kk\n\naa bb cc dd ee ff gg hh ii jj kk\n\naa bb cc dd ee";
+        let test_cases = [
+            (GapStrategy::Alternating, false),
+            (GapStrategy::SeededRandom(3), false),
+            (GapStrategy::SeededRandom(11), true),
+            (GapStrategy::LeftBiased, true),
+        ];
+        for &(gap_strategy, avoid_rivers) in &test_cases {
+            println!("gap_strategy: {:?}, avoid_rivers: {}", gap_strategy, avoid_rivers);
+            let formatter: Formatter = Formatter::new(16).gap_strategy(gap_strategy).avoid_rivers(avoid_rivers);
+            let expected: String = formatter.transform(input).unwrap();
+            assert_eq!(Editor::new(input, 16, formatter.options().clone()).unwrap().lines().join("\n"), expected);
+            #[cfg(feature = "std")]
+            {
+                let mut output: Vec<u8> = Vec::new();
+                transform_reader(input.as_bytes(), &mut output, 16, formatter.options().clone()).unwrap();
+                assert_eq!(String::from_utf8(output).unwrap(), expected);
+            }
+        }
+        let formatter: Formatter = Formatter::new(16).gap_strategy(GapStrategy::Alternating).pad_short_lines(false);
+        assert!(formatter.transform(input).unwrap().starts_with("aa  bb  cc dd ee\nff gg hh  ii  jj\nkk\n\naa bb cc  dd  ee\n"));   // Второй абзац начинается с четвёртой строки
+    }
+
+    #[test]
+    fn gap_limits() {
+        let test_cases = [
+            ("Lorem ipsum dolor", 12, 1, None, "Lorem  ipsum\ndolor"),
+            ("Lorem ipsum dolor", 12, 1, Some(1), "Lorem ipsum\ndolor"),
+            ("aa bb cc dd", 11, 1, None, "aa bb cc dd"),
+            ("aa bb cc dd", 11, 2, None, "aa   bb  cc\ndd"),
+            ("aa bb cc dd", 11, 2, Some(2), "aa  bb  cc\ndd"),
+            ("aa bb cc dd ee", 11, 2, Some(2), "aa bb cc dd\nee"),
+            ("aa bb cc dd ee", 11, 2, Some(3), "aa   bb  cc\ndd  ee"),
+        ];
+        for &(input, line_width, min_gap, max_gap, expected) in &test_cases {
+            println!("input: '{}', min_gap: {}, max_gap: {:?}", input, min_gap, max_gap);
+            let options: Options = Options { min_gap, max_gap, last_line: LastLineMode::Left, pad_short_lines: false, ..Options::default() };
+            let document: Document = Document::from_str_with(input, line_width, options).unwrap();
+            let text: String = document.format_to_string();
+            assert_eq!((text.as_str(), text.capacity()), (expected, expected.len()));
+        }
+    }
+
+    #[test]
+    fn avoid_rivers() {
+        let input: &str = "aa bb cc dd aa bb cc dd aa bb cc dd aa bb cc dd";
+        let document: Document = Formatter::new(12).avoid_rivers(true).document(input).unwrap();
+        let text: String = document.format_to_string();
+        let expected: &str = "aa  bb cc dd\naa bb cc  dd\naa  bb cc dd\naa bb cc  dd";
+        assert_eq!((text.as_str(), text.capacity()), (expected, expected.len()));
+        assert_eq!(document.format_lines().collect::<Vec<String>>().join("\n"), text);
+        assert_eq!(Formatter::new(12).document(input).unwrap().format_to_string(), "aa  bb cc dd\naa  bb cc dd\naa  bb cc dd\naa  bb cc dd");
+    }
+
+    #[test]
+    fn avoid_widows() {
+        let test_cases = [
+            ("Lorem ipsum dolor sit amet", 22, false, "Lorem ipsum dolor sit\namet"),
+            ("Lorem ipsum dolor sit amet", 22, true, "Lorem ipsum dolor\nsit amet"),
+            ("Lorem ipsum dolor sit amet", 14, true, "Lorem ipsum\ndolor sit amet"),
+            ("Lorem ipsum dolor", 12, true, "Lorem ipsum\ndolor"),
+            ("Lorem ipsum dolor sit amet\n\nLorem ipsum dolor sit amet", 22, true, "Lorem ipsum dolor\nsit amet\n\nLorem ipsum dolor\nsit amet"),
+            ("Lorem ipsum dolor sit consectetur", 22, true, "Lorem ipsum dolor\nsit consectetur"),
+        ];
+        for &(input, line_width, avoid_widows, expected) in &test_cases {
+            println!("input: '{}', line_width: {}, avoid_widows: {}", input, line_width, avoid_widows);
+            let formatter: Formatter = Formatter::new(line_width).alignment(Alignment::Left).pad_short_lines(false).avoid_widows(avoid_widows);
+            assert_eq!(formatter.transform(input).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn keep_pairs() {
+        let test_cases = [
+            ("It weighs 5 kg now", 11, BreakAlgorithm::Greedy, "It weighs 5\nkg now"),
+            ("It weighs 5 kg now", 11, BreakAlgorithm::Greedy, "It weighs\n5 kg now"),
+            ("Ask Dr. Smith today", 9, BreakAlgorithm::Greedy, "Ask\nDr. Smith\ntoday"),
+            ("Ask Dr. Smith today", 9, BreakAlgorithm::Optimal, "Ask\nDr. Smith\ntoday"),
+            ("Ask J. Smith", 8, BreakAlgorithm::Greedy, "Ask\nJ. Smith"),
+            ("See § 12 below", 6, BreakAlgorithm::Greedy, "See\n§ 12\nbelow"),
+            ("Dr. Jekyllandhyde", 8, BreakAlgorithm::Greedy, "Dr.\nJekyllandhyde"),
+        ];
+        for (index, &(input, line_width, break_algorithm, expected)) in test_cases.iter().enumerate() {
+            println!("input: '{}', line_width: {}, break_algorithm: {:?}", input, line_width, break_algorithm);
+            let mut formatter: Formatter = Formatter::new(line_width).alignment(Alignment::Left).pad_short_lines(false).overflow(OverflowPolicy::Overflow).break_algorithm(break_algorithm);
+            if index > 0 {
+                formatter = formatter
+                    .keep_pair(WordPattern::Number, WordPattern::Word("kg".to_string()))
+                    .keep_pair(WordPattern::Word("Dr.".to_string()), WordPattern::Any)
+                    .keep_pair(WordPattern::Initial, WordPattern::Any)
+                    .keep_pair(WordPattern::Word("§".to_string()), WordPattern::Number);
+            }
+            assert_eq!(formatter.transform(input).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn keep_together() {
+        let test_cases = [
+            ("Call New York City now", 13, &[][..], "Call New York\nCity now"),
+            ("Call New York City now", 13, &["New York City"][..], "Call\nNew York City\nnow"),
+            ("Call New York now", 8, &["New York City"][..], "Call New\nYork now"),
+            ("Call New\u{2060} York now", 9, &[][..], "Call\nNew\u{2060} York\nnow"),
+            ("Call New \u{2060}York now", 9, &[][..], "Call\nNew \u{2060}York\nnow"),
+            ("Вы\u{AD}\u{2060}ра\u{AD}же\u{AD}ние", 6, &[][..], "Вы\u{2060}ра-\nжение"),
+        ];
+        for &(input, line_width, phrases, expected) in &test_cases {
+            println!("input: '{:?}', line_width: {}, phrases: {:?}", input, line_width, phrases);
+            let formatter: Formatter = Formatter::new(line_width).alignment(Alignment::Left).pad_short_lines(false).overflow(OverflowPolicy::Overflow).keep_together(phrases);
+            assert_eq!(formatter.transform(input).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn break_at_hyphens() {
+        let test_cases = [
+            ("a state-of-the-art tool", 12, false, "Err"),
+            ("a state-of-the-art tool", 12, true, "a  state-of-\nthe-art tool"),
+            ("a state-of-the-art tool", 9, true, "a  state-\nof-the-  \nart  tool"),
+            ("pages 10–20 only", 9, true, "pages 10–\n20   only"),
+            ("a -- b-", 7, true, "a -- b-"),
+            ("well-\u{2060}known fact", 6, true, "Err"),
+        ];
+        for &(input, line_width, break_at_hyphens, expected) in &test_cases {
+            println!("input: '{:?}', line_width: {}, break_at_hyphens: {}", input, line_width, break_at_hyphens);
+            let formatter: Formatter = Formatter::new(line_width).break_at_hyphens(break_at_hyphens);
+            assert_eq!(formatter.transform(input).unwrap_or_else(|_| "Err".to_string()), expected);
+        }
+    }
+
+    #[test]
+    fn urls() {
+        let test_cases = [
+            ("see https://example.com/a/b?x=1&y=2 now", 14, false, "see https://\nexample.com/a/\nb?x=1&y=2 now"),
+            ("see https://example.com/a/b?x=1&y=2 now", 14, true, "see\nhttps://example.com/a/b?x=1&y=2\nnow"),
+            ("in /usr/local/bin/tool ok", 11, false, "in /usr/\nlocal/bin/\ntool ok"),
+            ("visit www.example.com", 10, false, "visit www.\nexample.\ncom"),
+            ("not.a.path at all", 6, false, "Err"),
+        ];
+        for &(input, line_width, keep_urls, expected) in &test_cases {
+            println!("input: '{}', line_width: {}, keep_urls: {}", input, line_width, keep_urls);
+            let formatter: Formatter = Formatter::new(line_width).alignment(Alignment::Left).pad_short_lines(false).keep_urls(keep_urls);
+            assert_eq!(formatter.transform(input).unwrap_or_else(|_| "Err".to_string()), expected);
+        }
+    }
+
+    #[test]
+    fn word_splitters() {
+        struct CamelCase;
+        impl WordSplitter for CamelCase {
+            fn split_points(&self, word: &str) -> Vec<(usize, bool)> {
+                word.char_indices().skip(1).filter(|(_, c)| c.is_uppercase()).map(|(index, _)| (index, false)).collect()
+            }
+        }
+        struct EveryThird;
+        impl WordSplitter for EveryThird {
+            fn split_points(&self, word: &str) -> Vec<(usize, bool)> {
+                (3..word.len()).step_by(3).map(|index| (index, true)).chain(Some((word.len(), true))).collect()
+            }
+        }
+
+        let test_cases: Vec<(Formatter, &str, &str)> = vec![
+            (Formatter::new(12).splitter(CamelCase), "use LineAdjustmentFormatter", "use     Line\nAdjustment  \nFormatter   "),
+            (Formatter::new(8).splitter(EveryThird), "abcdefghij", "abcdef- \nghij    "),
+            (Formatter::new(8).splitter(HyphenSplitter), "well-known", "well-   \nknown   "),
+            (Formatter::new(8).splitter(UrlSplitter), "/usr/local/bin", "/usr/   \nlocal/  \nbin     "),
+            (Formatter::new(5).splitter(CharSplitter), "ab abcdef", "ab ab\ncdef "),
+            (Formatter::new(5).overflow(OverflowPolicy::BreakAnywhere), "ab abcdef", "ab   \nabcde\nf    "),
+        ];
+        for (formatter, input, expected) in test_cases {
+            println!("input: '{}'", input);
+            assert_eq!(formatter.transform(input).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn sentence_spacing() {
+        let test_cases = [
+            ("One two. Three four five.", 26, Alignment::Justify, false, "One  two. Three four five."),
+            ("One two. Three four five.", 26, Alignment::Justify, true, "One two.  Three four five."),
+            ("One two. Three four five.", 28, Alignment::Justify, true, "One  two.  Three  four five."),
+            ("Go! Now? Yes. ok", 20, Alignment::Left, true, "Go!  Now?  Yes.  ok "),
+            ("Stop (really.) now", 19, Alignment::Left, true, "Stop (really.)  now"),
+            ("Stop (really.) now", 18, Alignment::Left, true, "Stop (really.)    \nnow               "),
+            ("One. Two", 10, Alignment::Right, true, " One.  Two"),
+        ];
+        for &(input, line_width, alignment, sentence_spacing, expected) in &test_cases {
+            println!("input: '{}', line_width: {}, alignment: {:?}, sentence_spacing: {}", input, line_width, alignment, sentence_spacing);
+            let formatter: Formatter = Formatter::new(line_width).alignment(alignment).last_line(LastLineMode::Justify).sentence_spacing(sentence_spacing);
+            assert_eq!(formatter.transform(input).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn french_spacing() {
+        let test_cases = [
+            ("Quoi ? Il dit : « oui » !", 28, Locale::Default, "Quoi  ?  Il  dit : « oui » !"),
+            ("Quoi ? Il dit : « oui » !", 28, Locale::French, "Quoi\u{202F}?  Il  dit\u{202F}:  «\u{202F}oui\u{202F}»\u{202F}!"),
+            ("Alors ? Bien", 7, Locale::French, "Alors\u{202F}?\nBien   "),
+            ("Un « mot » ici", 9, Locale::French, "Un       \n«\u{202F}mot\u{202F}»  \nici      "),
+        ];
+        for &(input, line_width, locale, expected) in &test_cases {
+            println!("input: '{}', line_width: {}, locale: {:?}", input, line_width, locale);
+            let formatter: Formatter = Formatter::new(line_width).last_line(LastLineMode::Justify).locale(locale);
+            assert_eq!(formatter.transform(input).unwrap(), expected);
+            let document: Document = Document::from_str_with(input, line_width, formatter.options().clone()).unwrap();
+            assert_eq!(document.format_to_string().capacity(), expected.len());
+        }
+        let formatter: Formatter = Formatter::new(12).alignment(Alignment::Left).locale(Locale::French).sentence_spacing(true);   // Узкий промежуток после конца предложения не расширяется
+        let text: String = formatter.transform("foo! ? bar").unwrap();
+        assert_eq!((text.as_str(), text.capacity()), ("foo!\u{202F}?  bar ", "foo!\u{202F}?  bar ".len()));
+    }
+
+    #[test]
+    fn hanging_punctuation() {
+        let test_cases = [
+            ("Lorem ipsum, dolor sit amet.", 11, Alignment::Justify, false, "Lorem      \nipsum,     \ndolor   sit\namet.      "),
+            ("Lorem ipsum, dolor sit amet.", 12, Alignment::Justify, true, "Lorem  ipsum,\ndolor    sit\namet.       "),
+            ("Lorem ipsum, dolor sit amet.", 11, Alignment::Justify, true, "Lorem ipsum,\ndolor   sit\namet.      "),
+            ("Lorem ipsum, dolor sit amet.", 12, Alignment::Right, true, " Lorem ipsum,\n   dolor sit\n        amet."),
+            ("Lorem ipsum, dolor sit amet.", 12, Alignment::Left, true, "Lorem ipsum,\ndolor sit   \namet.       "),
+            ("un-der-stand-ing", 6, Alignment::Justify, true, "un-der-\nstand-\ning   "),
+        ];
+        for &(input, line_width, alignment, hanging_punctuation, expected) in &test_cases {
+            println!("input: '{}', line_width: {}, alignment: {:?}, hanging_punctuation: {}", input, line_width, alignment, hanging_punctuation);
+            let options: Options = Options { alignment, last_line: LastLineMode::Left, hanging_punctuation, break_at_hyphens: true, ..Options::default() };
+            let text: String = Document::from_str_with(input, line_width, options).unwrap().format_to_string();
+            assert_eq!(text, expected);
+            assert_eq!(text.capacity(), text.len());
+        }
+    }
+
+    #[test]
+    fn proportional_widths() {
+        type Case<'a> = (&'a [f32], f32, f32, BreakAlgorithm, &'a [Range<usize>]);     // Ширины слов, пробела и строки
+        let test_cases: [Case; 7] = [
+            (&[], 2.5, 40.0, BreakAlgorithm::Greedy, &[]),
+            (&[10.5, 12.25, 8.0, 20.0], 2.5, 40.0, BreakAlgorithm::Greedy, &[0..3, 3..4]),
+            (&[10.5, 12.25, 8.0, 20.0], 2.5, 35.75, BreakAlgorithm::Greedy, &[0..3, 3..4]),
+            (&[10.5, 12.25, 8.0, 20.0], 2.5, 35.7, BreakAlgorithm::Greedy, &[0..2, 2..4]),
+            (&[3.0, 2.0, 2.0, 5.0], 1.0, 6.0, BreakAlgorithm::Greedy, &[0..2, 2..3, 3..4]),
+            (&[3.0, 2.0, 2.0, 5.0], 1.0, 6.0, BreakAlgorithm::Optimal, &[0..1, 1..3, 3..4]),
+            (&[4.0, 4.0, 4.0, 4.0, 4.0], 1.0, 19.0, BreakAlgorithm::Balanced, &[0..3, 3..5]),
+        ];
+        for (widths, space_width, line_width, break_algorithm, expected) in test_cases {
+            println!("widths: {:?}, space_width: {}, line_width: {}, break_algorithm: {:?}", widths, space_width, line_width, break_algorithm);
+            assert_eq!(wrap_f32(widths, space_width, line_width, break_algorithm), expected);
+        }
+    }
+
+    #[test]
+    fn layout() {
+        type Case<'a> = (&'a str, usize, Alignment, LastLineMode, &'a [&'a [(&'a str, usize)]]);
+        let test_cases: [Case; 5] = [
+            ("", 10, Alignment::Justify, LastLineMode::Left, &[]),
+            ("Lorem ipsum dolor", 12, Alignment::Justify, LastLineMode::Left, &[&[("Lorem", 0), ("ipsum", 7)], &[("dolor", 0)]]),
+            ("Lorem ipsum dolor", 12, Alignment::Right, LastLineMode::Right, &[&[("Lorem", 1), ("ipsum", 7)], &[("dolor", 7)]]),
+            ("Lorem ipsum dolor", 13, Alignment::Center, LastLineMode::Center, &[&[("Lorem", 1), ("ipsum", 7)], &[("dolor", 4)]]),
+            ("un\u{AD}der\u{AD}stand\u{AD}ing", 8, Alignment::Left, LastLineMode::Left, &[&[("un\u{AD}der\u{AD}", 0), ("-", 5)], &[("stand\u{AD}ing", 0)]]),
+        ];
+        for (input, line_width, alignment, last_line, expected) in test_cases {
+            println!("input: '{}', line_width: {}, alignment: {:?}, last_line: {:?}", input, line_width, alignment, last_line);
+            let options: Options = Options { alignment, last_line, ..Options::default() };
+            let document: Document = Document::from_str_with(input, line_width, options).unwrap();
+            assert_eq!(document.layout(), expected);
+        }
+    }
+
+    #[test]
+    fn spaced_words() {
+        type Case<'a> = (&'a str, usize, Options, &'a [(&'a [&'a str], &'a [u32])]);
+        let test_cases: [Case; 5] = [
+            ("", 10, Options::default(), &[]),
+            ("Lorem ipsum dolor sit amet", 13, Options { last_line: LastLineMode::Left, ..Options::default() }, &[(&["Lorem", "ipsum"], &[3]), (&["dolor", "sit"], &[5]), (&["amet"], &[])]),
+            ("Lorem ipsum dolor sit amet", 17, Options { last_line: LastLineMode::Left, ..Options::default() }, &[(&["Lorem", "ipsum", "dolor"], &[1, 1]), (&["sit", "amet"], &[1])]),
+            ("Lorem ipsum dolor sit amet", 18, Options { last_line: LastLineMode::Left, ..Options::default() }, &[(&["Lorem", "ipsum", "dolor"], &[2, 1]), (&["sit", "amet"], &[1])]),
+            ("Lorem ipsum dolor sit amet", 8, Options::default(), &[(&["Lorem"], &[]), (&["ipsum"], &[]), (&["dolor"], &[]), (&["sit", "amet"], &[1])]),
+        ];
+        for (input, line_width, options, expected) in test_cases {
+            println!("input: '{}', line_width: {}", input, line_width);
+            let document: Document = Document::from_str_with(input, line_width, options).unwrap();
+            let expected: Vec<(Vec<&str>, Vec<u32>)> = expected.iter().map(|&(words, gaps)| (words.to_vec(), gaps.to_vec())).collect();
+            assert_eq!(document.spaced_words(), expected);
+        }
+    }
+
+    #[test]
+    fn line_widths() {
+        let test_cases: [(&str, usize, &str); 4] = [
+            ("Lorem ipsum dolor sit amet", 0, "Lorem\nipsum dolor\nsit amet "),
+            ("Lorem ipsum dolor sit amet consectetur", 1, "Lorem\nipsum   \ndolor   sit\namet          \nconsectetur      "),
+            ("Lorem ipsum\n\ndolor sit amet", 2, "Lorem \nipsum \n\ndolor sit\namet     "),
+            ("Lorem ipsum dolor sit amet", 3, "Lorem ipsum\ndolor sit\namet     "),
+        ];
+        for (input, shape, expected) in test_cases {
+            println!("input: '{}', shape: {}", input, shape);
+            let line_width = |line: usize| match shape {
+                0 => [5, 11, 9][line.min(2)],                                        // Сужение, затем постоянная ширина
+                1 => 5 + 3 * line,                                                  // Треугольник
+                2 => if line < 2 { 6 } else { 9 },
+                _ => if line < 1 { 11 } else { 9 },
+            };
+            let options: Options = Options { last_line: LastLineMode::Left, ..Options::default() };
+            let document: Document = Document::from_str_with_widths(input, line_width, options).unwrap();
+            assert_eq!(document.format_to_string(), expected);
+        }
+        assert_eq!(Document::from_str_with_widths("Lorem ipsum", |line| if line < 1 { 5 } else { 0 }, Options::default()).map(|_| ()), Err(DocError::ZeroWidth));
+        let mut document: Document = Document::from_str_with_widths("Lorem ipsum", |line| [5, 11][line.min(1)], Options::default()).unwrap();
+        document.push_text("dolor sit").unwrap();
+        assert_eq!(document.format_to_string(), "Lorem\nipsum dolor\nsit        ");
+    }
+
+    #[test]
+    fn line_shape() {
+        let test_cases: [(&str, &[usize], &str); 4] = [
+            ("Lorem ipsum dolor sit amet", &[5, 11, 9], "Lorem\nipsum dolor\nsit amet "),
+            ("Lorem ipsum dolor sit amet", &[11], "Lorem ipsum\ndolor   sit\namet       "),
+            ("Lorem ipsum dolor sit amet consectetur", &[5, 8, 11, 14, 17], "Lorem\nipsum   \ndolor   sit\namet          \nconsectetur      "),
+            ("a b c d e f g h i", &[1, 3, 5, 3, 1], "a\nb c\nd e f\ng h\ni"),
+        ];
+        for (input, line_widths, expected) in test_cases {
+            println!("input: '{}', line_widths: {:?}", input, line_widths);
+            let options: Options = Options { last_line: LastLineMode::Left, ..Options::default() };
+            assert_eq!(Document::from_str_with_shape(input, line_widths, options).map(|document| document.format_to_string()), Ok(expected.to_string()));
+        }
+        assert_eq!(Document::from_str_with_shape("Lorem", &[], Options::default()).map(|_| ()), Err(DocError::ZeroWidth));
+        assert_eq!(Document::from_str_with_shape("Lorem", &[5, 0], Options::default()).map(|_| ()), Err(DocError::ZeroWidth));
+    }
+
+    #[test]
+    fn line_badness() {
+        let test_cases: [(usize, usize, usize, u32); 8] = [
+            (0, 0, 1, 0),
+            (3, 0, 1, 10000),
+            (1, 1, 1, 100),
+            (1, 2, 1, 12),
+            (2, 1, 1, 800),
+            (3, 2, 1, 337),
+            (6, 2, 2, 337),
+            (100, 1, 1, 10000),
+        ];
+        for (slack, gaps, space_width, expected) in test_cases {
+            println!("slack: {}, gaps: {}, space_width: {}", slack, gaps, space_width);
+            assert_eq!(badness(slack, gaps, space_width), expected);
+        }
+
+        let test_cases: [(&str, usize, u64); 4] = [
+            ("Lorem ipsum dolor sit amet", 26, 0),
+            ("Lorem ipsum dolor sit amet", 11, 800),
+            ("Lorem ipsum dolor sit amet", 13, 800 + 6400),
+            ("Lorem ipsum\n\ndolor sit amet", 12, 2700),
+        ];
+        for (input, line_width, expected) in test_cases {
+            println!("input: '{}', line_width: {}", input, line_width);
+            assert_eq!(Document::from_str(input, line_width).unwrap().total_badness(), expected);
+        }
+    }
+
+    #[test]
+    fn quality() {
+        let test_cases: [(&str, usize, Quality); 4] = [
+            ("", 10, Quality::default()),
+            ("Lorem ipsum dolor sit amet", 30, Quality { max_gap: 1, gap_variance: 0.0, overstretched_lines: 0, last_line_fill: 26.0 / 30.0 }),
+            ("Lorem ipsum dolor sit amet", 13, Quality { max_gap: 5, gap_variance: 1.0, overstretched_lines: 1, last_line_fill: 4.0 / 13.0 }),
+            ("Lorem ipsum dolor sit amet", 18, Quality { max_gap: 2, gap_variance: 0.2222222222222222, overstretched_lines: 0, last_line_fill: 8.0 / 18.0 }),
+        ];
+        for (input, line_width, expected) in test_cases {
+            println!("input: '{}', line_width: {}", input, line_width);
+            let options: Options = Options { last_line: LastLineMode::Left, stretch_limit: 4, ..Options::default() };
+            assert_eq!(Document::from_str_with(input, line_width, options).unwrap().quality(), expected);
+        }
+    }
+
+    #[test]
+    fn statistics() {
+        let test_cases: [(&str, usize, usize, usize, &[f64]); 4] = [
+            ("", 10, 0, 0, &[]),
+            ("Lorem ipsum dolor sit amet", 30, 1, 5, &[26.0 / 30.0]),
+            ("Lorem ipsum dolor sit amet", 13, 3, 5, &[11.0 / 13.0, 9.0 / 13.0, 4.0 / 13.0]),
+            ("Lorem\n\nipsum", 5, 2, 2, &[1.0, 1.0]),
+        ];
+        for (input, line_width, line_count, word_count, fills) in test_cases {
+            println!("input: '{}', line_width: {}", input, line_width);
+            let document: Document = Document::from_str(input, line_width).unwrap();
+            assert_eq!((document.line_count(), document.width(), document.word_count(), document.is_empty()), (line_count, line_width, word_count, input.is_empty()));
+            assert_eq!(document.lines().map(|line| line.fill()).collect::<Vec<f64>>(), fills);
+        }
+        let options: Options = Options { max_lines: Some(2), ..Options::default() };
+        assert_eq!(Document::from_str_with("Lorem ipsum dolor sit amet", 13, options).unwrap().line_count(), 2);
+    }
+
+    #[test]
+    fn verify_output() {
+        let test_cases: [(&str, &str, usize, &[Violation]); 9] = [
+            ("", "", 10, &[]),
+            ("Lorem ipsum dolor sit amet", "Lorem  ipsum\ndolor    sit\namet        ", 12, &[]),
+            ("Lorem ipsum dolor sit amet", "Lorem ipsum \ndolor    sit\namet        ", 12, &[]),
+            ("Lorem ipsum dolor sit amet", "Lorem ipsum dolor\nsit amet", 12, &[Violation::LineTooWide { line: 0, width: 17 }, Violation::LineTooShort { line: 1, width: 8 }]),
+            ("Lorem ipsum dolor sit amet", "Lorem   ipsum dolor\nsit amet           ", 19, &[Violation::UnevenGaps { line: 0, narrowest: 1, widest: 3 }]),
+            ("Lorem ipsum dolor sit amet", "Lorem  dolor\nipsum    sit\namet        ", 12, &[Violation::TextChanged { line: 0 }]),
+            ("Lorem ipsum dolor sit amet", "Lorem  ipsum\ndolor    sit", 12, &[Violation::TextChanged { line: 1 }]),
+            ("understanding", "under-  \nstanding", 8, &[]),
+            ("Lorem ipsum\n\ndolor", "Lorem ipsum\n           \ndolor      ", 11, &[]),
+        ];
+        for (input, output, line_width, expected) in test_cases {
+            println!("input: '{}', output: '{}', line_width: {}", input, output, line_width);
+            assert_eq!(verify(input, output, line_width, &Options::default()), expected);
+        }
+        for line_width in 11..24 {                                                  // Собственный вывод проходит проверку
+            let input: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit\n\nsed do eiusmod";
+            let output: String = transform(input, line_width).unwrap();
+            assert_eq!(verify(input, &output, line_width, &Options::default()), []);
+        }
+        assert_eq!(Violation::UnevenGaps { line: 2, narrowest: 1, widest: 3 }.to_string(), "line 3 has gaps from 1 to 3 spaces");
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn generator() {
+        use crate::Generator;
+        for seed in 0..200 {
+            let mut generator: Generator = Generator::new(seed);
+            let (input, line_width, options): (String, usize, Options) = (generator.text(20), generator.line_width(), generator.options());
+            println!("seed: {}, input: {:?}, line_width: {}, options: {:?}", seed, input, line_width, options);
+            let text: Result<String, DocError> = transform_with(&input, line_width, options.clone());
+            let mut again: Generator = Generator::new(seed);
+            assert_eq!(again.text(20), input);
+            assert_eq!(again.line_width(), line_width);
+            assert_eq!(transform_with(&input, line_width, again.options()), text);
+        }
+    }
+
+    #[test]
+    fn hyphen() {
+        let test_cases: [(&str, usize, &str, &str); 5] = [
+            ("un\u{AD}der\u{AD}stand\u{AD}ing it", 8, "-", "under-  \nstanding\nit      "),
+            ("un\u{AD}der\u{AD}stand\u{AD}ing it", 8, "\u{2010}", "under\u{2010}  \nstanding\nit      "),
+            ("un\u{AD}der\u{AD}stand\u{AD}ing it", 8, "", "under   \nstanding\nit      "),
+            ("un\u{AD}der\u{AD}stand\u{AD}ing it", 6, "", "under \nstand \ning it"),
+            ("un\u{AD}der\u{AD}stand\u{AD}ing it", 7, "--", "under--\nstand--\ning it "),
+        ];
+        for (input, line_width, hyphen, expected) in test_cases {
+            println!("input: '{}', line_width: {}, hyphen: '{}'", input, line_width, hyphen);
+            let formatter: Formatter = Formatter::new(line_width).hyphen(hyphen).last_line(LastLineMode::Left);
+            let text: String = formatter.transform(input).unwrap();
+            assert_eq!(text, expected);
+            assert_eq!(text.capacity(), text.len());
+        }
+    }
+
+    #[test]
+    fn from_words() {
+        let test_cases: [(&[&str], usize, &str); 5] = [
+            (&[], 10, ""),
+            (&["Lorem", "ipsum", "dolor", "sit", "amet"], 12, "Lorem  ipsum\ndolor    sit\namet        "),
+            (&["Lorem", "", "ipsum"], 11, "Lorem ipsum"),
+            (&["**Lorem ipsum**", "dolor"], 15, "**Lorem ipsum**\ndolor          "),
+            (&["Lorem\u{AD}ipsum", "dolor"], 8, "Lorem-  \nipsum   \ndolor   "),
+        ];
+        for (words, line_width, expected) in test_cases {
+            println!("words: {:?}, line_width: {}", words, line_width);
+            assert_eq!(Document::from_words(words.iter().copied(), line_width).unwrap().format_to_string(), expected);
+        }
+        let text: String = String::from("Lorem ipsum dolor");
+        let document: Document = Document::from_words(text.split(' '), 11).unwrap();
+        assert_eq!(DocumentOwned::from(&document).format_to_string(), "Lorem ipsum\ndolor      ");
+        assert_eq!(
+            Document::from_words(["Lorem", "consectetur"], 8).map(|_| ()),
+            Err(DocError::WordTooLong { word: String::from("consectetur"), range: 0..11, word_index: 1 })
+        );
+    }
+
+    #[test]
+    fn from_measured() {
+        type Case<'a> = (&'a [(&'a str, usize)], usize, &'a str);                  // Слова с ширинами, ширина строки, результат
+        let test_cases: [Case; 4] = [
+            (&[], 10, ""),
+            (&[("AA", 4), ("b", 1), ("c", 1)], 6, "AA b\nc     "),
+            (&[("AA", 2), ("b", 1), ("c", 1)], 6, "AA b c"),
+            (&[("Lorem", 3), ("ipsum", 3), ("dolor", 7)], 7, "Lorem ipsum\ndolor"),
+        ];
+        for (words, line_width, expected) in test_cases {
+            println!("words: {:?}, line_width: {}", words, line_width);
+            assert_eq!(Document::from_measured(words.iter().copied(), line_width).unwrap().format_to_string(), expected);
+        }
+        assert_eq!(
+            Document::from_measured([("Lorem", 5), ("ipsum", 9)], 8).map(|_| ()),
+            Err(DocError::WordTooLong { word: String::from("ipsum"), range: 0..5, word_index: 1 })
+        );
+    }
+
+    #[test]
+    fn tokenizer() {
+        struct Markup;                                                              // Пробелы внутри "**...**" и "<...>" не делят слово
+        impl Tokenizer for Markup {
+            fn words<'a>(&self, text: &'a str) -> Vec<&'a str> {
+                let mut words: Vec<&str> = Vec::new();
+                let (mut start, mut bold, mut tag): (Option<usize>, bool, bool) = (None, false, false);
+                for (index, c) in text.char_indices() {
+                    if c.is_whitespace() && !bold && !tag {
+                        words.extend(start.take().map(|start| &text[start..index]));
+                        continue;
+                    }
+                    start.get_or_insert(index);
+                    match c {
+                        '*' if text[index..].starts_with("**") && !text[..index].ends_with('*') => bold = !bold,
+                        '<' => tag = true,
+                        '>' => tag = false,
+                        _ => {}
+                    }
+                }
+                words.extend(start.map(|start| &text[start..]));
+                words
+            }
+        }
+        struct Foreign;                                                             // Слова не из текста отбрасываются
+        impl Tokenizer for Foreign {
+            fn words<'a>(&self, _: &'a str) -> Vec<&'a str> {
+                vec!["foreign", ""]
+            }
+        }
+
+        let input: &str = "say **very bold** <a href=x>now</a>";
+        let test_cases: Vec<(Formatter, &str)> = vec![
+            (Formatter::new(20), "say **very bold** <a\nhref=x>now</a>      "),
+            (Formatter::new(20).tokenizer(WhitespaceTokenizer), "say **very bold** <a\nhref=x>now</a>      "),
+            (Formatter::new(20).tokenizer(Markup), "say    **very bold**\n<a href=x>now</a>   "),
+            (Formatter::new(20).tokenizer(Foreign), ""),
+        ];
+        for (formatter, expected) in test_cases {
+            println!("tokenizer: {:?}", formatter.options().tokenizer);
+            assert_eq!(formatter.transform(input).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn collapse_whitespace() {
+        let test_cases: [(&str, usize, Alignment, bool, &str); 8] = [
+            ("Lorem     ipsum", 20, Alignment::Left, true, "Lorem ipsum         "),
+            ("Lorem     ipsum", 20, Alignment::Left, false, "Lorem     ipsum     "),
+            ("Lorem     ipsum", 20, Alignment::Right, false, "     Lorem     ipsum"),
+            ("a   b", 9, Alignment::Center, false, "  a   b  "),
+            ("Lorem     ipsum", 20, Alignment::Justify, false, "Lorem          ipsum"),
+            ("Lorem   ipsum\n  dolor", 20, Alignment::Left, false, "Lorem   ipsum dolor "),
+            ("Lorem      ipsum dolor", 12, Alignment::Left, false, "Lorem       \nipsum dolor "),
+            ("Lorem\u{3000}\u{3000}ipsum", 12, Alignment::Left, false, "Lorem  ipsum"),
+        ];
+        for (input, line_width, alignment, collapse_whitespace, expected) in test_cases {
+            println!("input: '{}', line_width: {}, alignment: {:?}, collapse_whitespace: {}", input, line_width, alignment, collapse_whitespace);
+            let formatter: Formatter = Formatter::new(line_width).alignment(alignment).collapse_whitespace(collapse_whitespace);
+            assert_eq!(formatter.transform(input).unwrap(), expected);
+        }
+        let options: Options = Options { alignment: Alignment::Left, collapse_whitespace: false, ..Options::default() };
+        let document: Document = Document::from_str_with("Lorem     ipsum", 20, options).unwrap();
+        assert_eq!(document.layout(), vec![vec![("Lorem", 0), ("ipsum", 10)]]);
+        assert_eq!(document.spaced_words(), vec![(vec!["Lorem", "ipsum"], vec![5])]);
+        assert_eq!(document.format_to_string().len(), document.text_length());
+    }
+
+    #[test]
+    fn preformatted() {
+        let input: &str = "Lorem ipsum dolor\n    let x  =  1;\n\tthe tab line that is too long\nsit amet";
+        let test_cases: [(Option<usize>, &str); 4] = [
+            (None, "Lorem ipsum\ndolor let x\n=   1;  the\ntab    line\nthat is too\nlong    sit\namet       "),
+            (Some(4), "Lorem ipsum\ndolor      \n    let x  =  1;\n\tthe tab line that is too long\nsit amet   "),
+            (Some(2), "Lorem ipsum\ndolor      \n    let x  =  1;\n\tthe tab line that is too long\nsit amet   "),
+            (Some(5), "Lorem ipsum\ndolor let x\n= 1;       \n\tthe tab line that is too long\nsit amet   "),
+        ];
+        for (preformatted, expected) in test_cases {
+            println!("preformatted: {:?}", preformatted);
+            let options: Options = Options { preformatted, last_line: LastLineMode::Left, ..Options::default() };
+            assert_eq!(transform_with(input, 11, options).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn tabs() {
+        let test_cases: [(&str, TabPolicy, bool, Option<usize>, &str); 7] = [
+            ("Lorem\tipsum", TabPolicy::TreatAsSpace, true, None, "Lorem  ipsum"),
+            ("Lorem\tipsum", TabPolicy::ExpandTo(8), true, None, "Lorem  ipsum"),
+            ("ab\tcd", TabPolicy::TreatAsSpace, false, None, "ab cd       "),
+            ("ab\tcd", TabPolicy::ExpandTo(8), false, None, "ab      cd  "),
+            ("ab cd\tef", TabPolicy::ExpandTo(4), false, None, "ab cd   ef  "),
+            ("\tx\ty", TabPolicy::TreatAsSpace, true, Some(4), "\tx\ty"),
+            ("\tx\ty", TabPolicy::ExpandTo(4), true, Some(4), "    x   y"),
+        ];
+        for (input, tabs, collapse_whitespace, preformatted, expected) in test_cases {
+            println!("input: {:?}, tabs: {:?}, collapse_whitespace: {}, preformatted: {:?}", input, tabs, collapse_whitespace, preformatted);
+            let alignment: Alignment = if collapse_whitespace { Alignment::Justify } else { Alignment::Left };
+            let options: Options = Options { tabs, collapse_whitespace, preformatted, alignment, ..Options::default() };
+            assert_eq!(transform_with(input, 12, options), Ok(expected.to_string()));
+        }
+
+        let options: Options = Options { tabs: TabPolicy::Error, ..Options::default() };
+        let (document, error) = Document::from_str_partial("Lorem ipsum\tdolor", 11, options.clone());
+        assert_eq!((document.format_to_string(), error), (String::from("Lorem ipsum"), Some(DocError::Tab { offset: 11 })));
+        let mut document: Document = Document::from_str_with("Lorem", 11, options).unwrap();
+        assert_eq!(document.push_text(" ipsum\tdolor"), Err(DocError::Tab { offset: 6 }));
+        assert_eq!(document.format_to_string(), "Lorem      ");
+    }
+
+    #[test]
+    fn zero_width() {
+        let test_cases: Vec<(Formatter, &str, &str)> = vec![
+            (Formatter::new(20), "Lorem\u{200B}ipsum dolor", "Lorem\u{200B}ipsum     dolor"),
+            (Formatter::new(8), "Lorem\u{200B}ipsum dolor", "Lorem\u{200B}   \nipsum   \ndolor   "),
+            (Formatter::new(8), "\u{200B}Lorem\u{200B}", "\u{200B}Lorem\u{200B}   "),
+            (Formatter::new(6), "\u{2764}\u{FE0F} ok", "\u{2764}\u{FE0F}   ok"),
+            (Formatter::new(6), "a\u{200C}b\u{200D}c ok", "a\u{200C}b\u{200D}c ok"),
+            (Formatter::new(3).splitter(CharSplitter), "abc\u{200D}def", "ab \nc\u{200D}de\nf  "),
+            (Formatter::new(3).splitter(CharSplitter), "ab\u{2764}\u{FE0F}cd", "ab\u{2764}\u{FE0F}\ncd "),
+            (Formatter::new(6).width_mode(WidthMode::Columns), "\u{2764}\u{FE0F} ok", "\u{2764}\u{FE0F}   ok"),
+            (Formatter::new(7).width_mode(WidthMode::Bytes), "a\u{200B} ok", "a\u{200B} ok"),
+        ];
+        for (formatter, input, expected) in test_cases {
+            println!("input: {:?}", input);
+            assert_eq!(formatter.transform(input).unwrap(), expected);
+        }
+        #[cfg(feature = "graphemes")]
+        assert_eq!(Formatter::new(6).width_mode(WidthMode::Graphemes).transform("a\u{200B}b ok").unwrap(), "a\u{200B}b  ok");
+    }
+
+    #[test]
+    fn format_lines() {
+        let input: &str = "Lorem ipsum dolor sit amet\n\nconsectetur adipiscing\n\nelit";
+        for separator in ["\n", "", "* * *\n", "--"] {
+            println!("separator: '{}'", separator);
+            let options: Options = Options { paragraph_separator: separator.to_string(), ..Options::default() };
+            let document: Document = Document::from_str_with(input, 12, options).unwrap();
+            let lines: Vec<String> = document.format_lines().collect();
+            assert_eq!(lines, document.format_to_string().split('\n').collect::<Vec<&str>>());
+        }
+        assert_eq!(Document::from_str("Lorem ipsum dolor", 12).unwrap().format_lines().nth(1), Some("dolor       ".to_string()));
+        assert_eq!(Document::from_str("", 12).unwrap().format_lines().count(), 0);
+        assert_eq!(Document::from_str("Lorem ipsum dolor\n\nsit", 12).unwrap().into_lines(), vec!["Lorem  ipsum", "dolor       ", "", "sit         "]);
+    }
+
+    #[test]
+    fn borrowed_output() {
+        let test_cases = [
+            ("Lorem  ipsum\ndolor    sit\namet        ", true),
+            ("Lorem ipsum\ndolor sit\namet", false),
+            ("Lorem  ipsum\ndolor    sit\namet        \n", false),
+            ("", true),
+        ];
+
+        for (input, borrowed) in test_cases {
+            println!("input: '{}'", input);
+            let output: Cow<str> = transform_cow(input, 12).unwrap();
+            assert_eq!(matches!(output, Cow::Borrowed(_)), borrowed);
+            assert_eq!(output, transform(input, 12).unwrap());
+        }
+        assert_eq!(transform_cow("abc_abc_abc_abc_abc", 12), Err(word_too_long("abc_abc_abc_abc_abc", 0..19, 0)));
+        assert!(matches!(Formatter::new(12).alignment(Alignment::Left).transform_cow("Lorem ipsum "), Ok(Cow::Borrowed(_))));
+    }
+
+    #[test]
+    fn reflow() {
+        let input: &str = "Lorem ipsum dolor sit\u{AD}ting amet\n\nconsectetur adipiscing elit";
+        let mut document: Document = Document::from_str(input, 12).unwrap();
+        let mut previous: String = document.format_to_string();
+        for line_width in [20, 5, 30, 12] {
+            println!("line_width: {}", line_width);
+            let result = document.reflow(line_width);
+            match transform(input, line_width) {
+                Ok(expected) => {
+                    assert_eq!(result, Ok(()));
+                    assert_eq!(document.format_to_string(), expected);
+                }
+                Err(error) => {
+                    assert_eq!(result, Err(error));
+                    assert_eq!(document.format_to_string(), previous);
+                }
+            }
+            previous = document.format_to_string();
+        }
+    }
+
+    #[test]
+    fn push_text() {
+        let mut document: Document = Document::new(12, Options::default());
+        document.push_text("Lorem ipsum").unwrap();
+        document.push_word("dolor").unwrap();
+        document.push_text("sit amet\n\nconsectetur").unwrap();
+        assert_eq!(document.format_to_string(), transform("Lorem ipsum dolor sit amet\n\nconsectetur", 12).unwrap());
+
+        document.push_text(" adipiscing").unwrap();
+        document.end_paragraph();
+        document.push_word("10\u{20}km").unwrap();
+        assert_eq!(document.push_word("abc_abc_abc_abc_abc"), Err(word_too_long("abc_abc_abc_abc_abc", 0..19, 8)));
+        document.push_text("   ").unwrap();
+        assert_eq!(document.format_to_string(), transform("Lorem ipsum dolor sit amet\n\nconsectetur adipiscing\n\n10\u{A0}km", 12).unwrap().replace('\u{A0}', " "));
+
+        let mut document: Document = Document::from_str("Lorem ipsum", 12).unwrap();
+        document.push_text("dolor\nsit").unwrap();
+        assert_eq!(document.format_to_string(), transform("Lorem ipsum dolor\nsit", 12).unwrap());
+    }
+
+    #[test]
+    fn push_text_split_words() {
+        let test_cases = [
+            ("Lorem ipsum dolor sit amet,\n\nconsectetur  adipiscing elit", NewlinePolicy::ParagraphOnly),
+            ("Lorem ipsum\ndolor sit amet,\n\n\nconsectetur\nadipiscing elit\n", NewlinePolicy::Preserve),
+            ("Lorem ipsum dolor\n\nsit amet", NewlinePolicy::Collapse),
+        ];
+        for &(input, newlines) in &test_cases {
+            let options: Options = Options { newlines, ..Options::default() };
+            for size in 1..8 {                                                      // Куски по size байт рвут слова где придётся
+                println!("input: '{}', newlines: {:?}, size: {}", input, newlines, size);
+                let mut document: DocumentOwned = DocumentOwned::new(12, options.clone());
+                for chunk in input.as_bytes().chunks(size) {
+                    document.push_text(core::str::from_utf8(chunk).unwrap()).unwrap();
+                }
+                assert_eq!(document.format_to_string(), transform_with(input, 12, options.clone()).unwrap());
+            }
+        }
+        let mut document: DocumentOwned = DocumentOwned::from_str_with("Lorem\n\nipsum do", 12, Options::default()).unwrap();
+        document.push_text("lor sit").unwrap();
+        assert_eq!(document.format_to_string(), transform("Lorem\n\nipsum dolor sit", 12).unwrap());
+
+        let mut document: DocumentOwned = DocumentOwned::new(12, Options::default());
+        document.push_text("Lorem ip").unwrap();
+        document.push_text("sum").unwrap();
+        document.end_paragraph();
+        document.push_text("do").unwrap();
+        assert_eq!(document.push_text("lor_abc_abc_abc"), Err(word_too_long("dolor_abc_abc_abc", 0..17, 2)));
+        document.push_text("lor sit").unwrap();
+        assert_eq!(document.format_to_string(), transform("Lorem ipsum\n\ndolor sit", 12).unwrap());
+
+        let mut document: Document = Document::new(12, Options::default());
+        document.push_text("Lorem ip").unwrap();
+        document.push_text("sum").unwrap();                                         // Заимствованные куски не склеить - это два слова
+        assert_eq!(document.format_to_string(), transform("Lorem ip sum", 12).unwrap());
+    }
+
+    #[test]
+    fn line_views() {
+        let document: Document = Document::from_str("Lorem ipsum dolor sit\u{AD}ting amet\n\nelit", 12).unwrap();
+        let lines: Vec<(&[&str], usize, bool, bool)> =
+            document.lines()
+                .map(|line| (line.words(), line.text_width(), line.hyphenated(), line.ends_paragraph()))
+                .collect();
+        assert_eq!(lines, vec![
+            (&["Lorem", "ipsum"][..], 10, false, false),
+            (&["dolor", "sit\u{AD}"][..], 9, true, false),
+            (&["ting", "amet"][..], 8, false, true),
+            (&["elit"][..], 4, false, true),
+        ]);
+    }
+
+    #[test]
+    fn overflow_policy() {
+        let test_cases = [
+            (OverflowPolicy::Error, Err(word_too_long("abcdefghijkl", 2..14, 1))),
+            (OverflowPolicy::BreakAnywhere, Ok("a    \nabcde\nfghij\nkl  b")),
+            (OverflowPolicy::Overflow, Ok("a    \nabcdefghijkl\nb    ")),
+            (OverflowPolicy::Truncate, Ok("a    \nabcde\nb    ")),
+        ];
+
+        for (overflow, expected) in test_cases {
+            println!("overflow: {:?}", overflow);
+            let options: Options = Options { overflow, ..Options::default() };
+            assert_eq!(transform_with("a abcdefghijkl b", 5, options), expected.map(str::to_string));
+        }
+    }
+
+    #[test]
+    fn soft_hyphens() {
+        let test_cases = [
+            ("extra\u{AD}ordinary words", 20, "extraordinary  words"),
+            ("extra\u{AD}ordinary words", 8, "extra-  \nordinary\nwords   "),
+            ("co\u{AD}op\u{AD}er\u{AD}ate now", 8, "cooper- \nate  now"),
+            ("\u{AD}lead trail\u{AD}", 5, "lead \ntrail"),
+        ];
+
+        for &(input, line_width, expected) in &test_cases {
+            println!("input: '{}'", input);
+            assert_eq!(transform(input, line_width), Ok(expected.to_string()));
+        }
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn ffi() {
+        use std::ffi::{CStr, CString};
+        use crate::ffi::{la_document_format, la_document_free, la_document_new, la_document_new_with, la_options_default, la_result_free, la_result_ok, la_result_text, LaOptions};
+        use crate::ffi::{LA_ALGORITHM_OPTIMAL, LA_ALIGN_LEFT, LA_LAST_LINE_RIGHT};
+
+        let test_cases = [
+            ("Lorem ipsum dolor sit amet", 12, true, "Lorem  ipsum\ndolor    sit\namet        "),
+            ("abc_abc_abc_abc", 8, false, "word \"abc_abc_abc_abc\" (word 1 at bytes 0..15) does not fit into the line width"),
+        ];
+        for &(input, line_width, ok, expected) in &test_cases {
+            println!("input: '{}', line_width: {}", input, line_width);
+            let input: CString = CString::new(input).unwrap();
+            unsafe {
+                let document = la_document_new(input.as_ptr(), line_width);
+                let result = la_document_format(document);
+                assert_eq!(la_result_ok(result), ok);
+                assert_eq!(CStr::from_ptr(la_result_text(result)).to_str(), Ok(expected));
+                la_result_free(result);
+                la_document_free(document);
+            }
+        }
+        let test_cases = [
+            (LaOptions { alignment: LA_ALIGN_LEFT, ..la_options_default() }, Some(Formatter::new(12).alignment(Alignment::Left))),
+            (LaOptions { last_line: LA_LAST_LINE_RIGHT, ..la_options_default() }, Some(Formatter::new(12).last_line(LastLineMode::Right))),
+            (LaOptions { algorithm: LA_ALGORITHM_OPTIMAL, ..la_options_default() }, Some(Formatter::new(12).break_algorithm(BreakAlgorithm::Optimal))),
+            (la_options_default(), Some(Formatter::new(12))),
+            (LaOptions { alignment: 4, ..la_options_default() }, None),
+            (LaOptions { algorithm: u32::MAX, ..la_options_default() }, None),
+        ];
+        let input: CString = CString::new("Lorem ipsum dolor sit amet, consectetur").unwrap();
+        for (options, formatter) in test_cases {
+            println!("options: {:?}", options);
+            unsafe {
+                let document = la_document_new_with(input.as_ptr(), 12, &options);
+                let Some(formatter) = formatter else {
+                    assert!(document.is_null());
+                    continue;
+                };
+                for _ in 0..2 {                                                     // Документ форматируется повторно без нового разбиения
+                    let result = la_document_format(document);
+                    assert_eq!(CStr::from_ptr(la_result_text(result)).to_str().map(str::to_string), Ok(formatter.transform(input.to_str().unwrap()).unwrap()));
+                    la_result_free(result);
+                }
+                la_document_free(document);
+            }
+        }
+        unsafe {
+            assert!(la_document_new_with(input.as_ptr(), 12, std::ptr::null()).is_null());
+            assert!(la_document_new(std::ptr::null(), 10).is_null());
+            assert!(la_document_new(c"\xFF".as_ptr(), 10).is_null());
+            assert!(la_document_format(std::ptr::null()).is_null());
+            assert!(!la_result_ok(std::ptr::null()));
+        }
+    }
+
+    #[cfg(feature = "hyphenation")]
+    #[test]
+    fn hyphenation_patterns() {
+        use crate::Hyphenator;
+
+        let tex: &str = "% Шаблоны для проверки\n\\patterns{ % a1b\n1na\nab1a\n}\n\\hyphenation{ba-na-na}\n";
+        let test_cases: [(&str, &str, &[usize]); 5] = [
+            (tex, "banana", &[2, 4]),                                               // Из \hyphenation
+            (tex, "Bananas", &[2, 4]),
+            (tex, "abab", &[]),
+            ("1na ab1a", "cabana", &[3]),
+            ("", "banana", &[]),
+        ];
+        for (patterns, word, expected) in test_cases {
+            println!("patterns: {:?}, word: '{}'", patterns, word);
+            assert_eq!(Hyphenator::from_pattern_text(patterns).hyphenate(word), expected);
+            #[cfg(feature = "std")]
+            assert_eq!(Hyphenator::from_patterns(patterns.as_bytes()).unwrap().hyphenate(word), expected);
+        }
+    }
+
+    #[cfg(feature = "hyphenation")]
+    #[test]
+    fn hyphenation_exceptions() {
+        use crate::Hyphenator;
+
+        let mut hyphenator: Hyphenator = Hyphenator::sample_english();
+        for word in ["ta-ble", "pro-ject", "Lon-don", "hyphenation", "a--b-"] {
+            hyphenator.add_exception(word);
+        }
+        let test_cases: [(&str, &[usize]); 7] = [
+            ("table", &[2]),
+            ("Tables", &[]),
+            ("\"Project,\"", &[4]),
+            ("LONDON", &[3]),
+            ("hyphenation", &[]),
+            ("ab", &[1]),
+            ("question", &[4]),
+        ];
+        for (word, expected) in test_cases {
+            println!("word: '{}'", word);
+            assert_eq!(hyphenator.hyphenate(word), expected);
+        }
+        assert_eq!(Hyphenator::from_pattern_text("\\patterns{1na}\n\\hyphenation{ba-nana}").hyphenate("banana"), [2]);
+        assert_eq!(Hyphenator::from_pattern_text("\\patterns{1na}\n\\hyphenation{banan-a}").hyphenate("banana"), [5]);
+    }
+
+    #[cfg(feature = "hyphenation")]
+    #[test]
+    fn hyphenation_limits() {
+        use crate::Hyphenator;
+
+        let test_cases: [(usize, usize, &str, &[usize]); 6] = [
+            (2, 3, "hyphenation", &[2, 6]),
+            (3, 3, "hyphenation", &[6]),
+            (2, 6, "hyphenation", &[2]),
+            (0, 0, "hyphenation", &[2, 6]),
+            (2, 2, "question", &[4]),
+            (5, 3, "question", &[]),
+        ];
+        for (min_before, min_after, word, expected) in test_cases {
+            println!("min_before: {}, min_after: {}, word: '{}'", min_before, min_after, word);
+            let hyphenator: Hyphenator = Hyphenator::sample_english().min_before(min_before).min_after(min_after);
+            assert_eq!(hyphenator.hyphenate(word), expected);
+        }
+    }
+
+    #[cfg(all(feature = "hyphenation", feature = "std"))]
+    #[test]
+    fn hyphenation_cache() {
+        use crate::Hyphenator;
+
+        let plain: Hyphenator = Hyphenator::sample_english();
+        for capacity in [0, 1, 2, 100] {
+            println!("capacity: {}", capacity);
+            let mut cached: Hyphenator = Hyphenator::sample_english().with_cache(capacity);
+            for word in ["hyphenation", "question", "hyphenation", "Table,", "question", "hyphenation", "bring"] {
+                assert_eq!(cached.hyphenate(word), plain.hyphenate(word));
+            }
+            cached.add_exception("hy-phen-ation");
+            assert_eq!(cached.hyphenate("hyphenation"), [2, 6]);
+            assert_eq!(cached.clone().hyphenate("hyphenation"), [2, 6]);
+            assert_eq!(Hyphenator::sample_english().with_cache(capacity), plain);
+        }
+    }
+
+    #[cfg(feature = "hyphenation")]
+    #[test]
+    fn hyphenation() {
+        use crate::Hyphenator;
+
+        let hyphenator: Hyphenator = Hyphenator::sample_english();
+        let test_words = [
+            ("hyphenation", vec![2, 6]),
+            ("Table,", vec![2]),
+            ("question", vec![4]),
+            ("bring", vec![]),
+            ("o'clock", vec![]),
+        ];
+        for (word, expected) in &test_words {
+            println!("word: '{}'", word);
+            assert_eq!(&hyphenator.hyphenate(word), expected);
+        }
+
+        let test_cases = [
+            ("The hyphenation of words", 10, "The    hy-\nphenation \nof   words"),
+            ("unbelievable", 5, "Err"),
+        ];
+        let options: Options = Options { hyphenator: Some(Arc::new(hyphenator)), ..Options::default() };
+        for &(input, line_width, expected) in &test_cases {
+            println!("input: '{}'", input);
+            assert_eq!(transform_with(input, line_width, options.clone()).unwrap_or_else(|_| "Err".to_string()), expected);
+            assert_eq!(Formatter::new(line_width).sample_hyphenation(true).transform(input).unwrap_or_else(|_| "Err".to_string()), expected);
         }
-        assert_eq!(transform("abc_abc_abc_abc_abc", 12), Err(DocError::WordTooLong));
+        assert_eq!(Formatter::new(10).sample_hyphenation(true).sample_hyphenation(false).transform("The hyphenation"), Err(word_too_long("hyphenation", 4..15, 1)));
     }
 }
@@ -0,0 +1,54 @@
+use alloc::vec::Vec;
+
+use crate::{line_body, Options};
+
+#[derive(Default)]
+pub(crate) struct Fence {                                                           // Следит, открыт ли блок кода с оградой ``` или ~~~
+    open: Option<&'static str>,
+}
+
+impl Fence {
+    pub(crate) fn line(&mut self, line: &str) -> bool {                            // Относится ли строка (вместе с самими оградами) к блоку кода
+        let marker: Option<&'static str> = ["```", "~~~"].into_iter().find(|marker| line.trim_start().starts_with(marker));
+        match (self.open, marker) {
+            (None, Some(marker)) => self.open = Some(marker),
+            (Some(open), Some(marker)) if open == marker => self.open = None,
+            (None, None) => return false,
+            _ => {}
+        }
+        true
+    }
+}
+
+fn is_heading(line: &str) -> bool {                                                 // Заголовок вида "## Текст"
+    let line: &str = line.trim_start();
+    let level: usize = line.len() - line.trim_start_matches('#').len();
+    (1..=6).contains(&level) && line[level..].chars().next().is_none_or(char::is_whitespace)
+}
+
+fn is_table_row(line: &str) -> bool {
+    line.trim_start().starts_with('|')
+}
+
+fn is_preformatted(line: &str, options: &Options) -> bool {                       // Строка с отступом Options::preformatted
+    options.preformatted.is_some_and(|indent| line.starts_with('\t') || line.len() - line.trim_start_matches(' ').len() >= indent.max(1))
+}
+
+pub(crate) fn segments<'a>(paragraph: &'a str, options: &Options) -> Vec<(&'a str, bool)> {   // Куски абзаца подряд идущих строк: текст или то, что выводится как есть
+    let mut segments: Vec<(&str, bool)> = Vec::new();
+    let mut fence: Fence = Fence::default();
+    let (mut start, mut offset): (usize, usize) = (0, 0);
+    let mut current: Option<bool> = None;
+    for line in paragraph.split_inclusive('\n') {
+        let body: &str = line_body(line, options);
+        let verbatim: bool = (options.markdown && (fence.line(body) || is_heading(body) || is_table_row(body))) || is_preformatted(body, options);
+        if let Some(kind) = current.filter(|&kind| kind != verbatim) {
+            segments.push((&paragraph[start..offset], kind));
+            start = offset;
+        }
+        current = Some(verbatim);
+        offset += line.len();
+    }
+    segments.extend(current.map(|kind| (&paragraph[start..], kind)));
+    segments
+}
@@ -0,0 +1,469 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+#[cfg(feature = "hyphenation")]
+use crate::Hyphenator;
+use crate::{transform_comment, transform_cow_with, DocError, Document, Measure, Segmenter, Tokenizer, WidthMode, WordSplitter};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Alignment {
+    Left,
+    Right,
+    Center,
+    Justify,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LastLineMode {
+    Justify,
+    Left,
+    Center,
+    Right,
+}
+
+impl LastLineMode {
+    pub(crate) fn alignment(self) -> Alignment {
+        match self {
+            LastLineMode::Justify => Alignment::Justify,
+            LastLineMode::Left => Alignment::Left,
+            LastLineMode::Center => Alignment::Center,
+            LastLineMode::Right => Alignment::Right,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BreakAlgorithm {
+    Greedy,
+    Optimal,                                    // Минимизирует сумму квадратов недозаполнения строк по всему абзацу
+    Balanced,                                   // Строки примерно одинаковой длины при том же числе строк, что и у Greedy
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OverflowPolicy {
+    Error,
+    BreakAnywhere,                              // Разрезать слово на куски по ширине строки без знака переноса
+    Overflow,                                   // Оставить слово целиком, выходя за ширину строки
+    Truncate,                                   // Отбросить не поместившийся остаток слова
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NewlinePolicy {
+    Collapse,                                   // Любые переводы строк - обычные пробелы, весь текст - один абзац
+    Preserve,                                   // Каждый перевод строки сохраняется, длинные строки переносятся
+    ParagraphOnly,                              // Только пустые строки разделяют абзацы
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TabPolicy {
+    ExpandTo(usize),                            // Табуляция - пробелы до следующей позиции, кратной числу, от начала входной строки (в сохранённых промежутках и строках, выводимых как есть)
+    TreatAsSpace,                               // Табуляция - обычный пробел между словами; строки, выводимые как есть, сохраняют её
+    Error,                                      // Табуляция во входном тексте - DocError::Tab
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Direction {
+    Ltr,                                        // Лишние пробелы от деления достаются первым промежуткам строки
+    Rtl,                                        // Лишние пробелы достаются последним промежуткам - началу строки при чтении справа налево
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GapStrategy {                          // Каким промежуткам строки достаются лишние пробелы от деления
+    LeftBiased,
+    RightBiased,
+    CenterOut,                                  // Сначала средним
+    Alternating,                                // Слева в чётных строках, справа в нечётных
+    SeededRandom(u64),                          // Псевдослучайно, но одинаково при одном зерне
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Locale {                               // Правила набора промежутков у знаков препинания
+    Default,
+    French,                                     // Узкий неразрывный пробел перед ";:!?»" и после "«" вместо обычного промежутка
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum WordPattern {                          // Слово из правила Options::keep_pairs
+    Word(String),
+    Number,                                     // Цифры, возможно с разделителями: "5", "3.5", "1,000"
+    Initial,                                    // Буква с точкой: "J."
+    Any,
+}
+
+impl WordPattern {
+    pub(crate) fn matches(&self, word: &str) -> bool {
+        match self {
+            WordPattern::Word(pattern) => word == pattern,
+            WordPattern::Number => word.starts_with(|c: char| c.is_ascii_digit()) && word.chars().all(|c| c.is_ascii_digit() || c == '.' || c == ','),
+            WordPattern::Initial => {
+                let mut chars = word.chars();
+                matches!((chars.next(), chars.next(), chars.next()), (Some(letter), Some('.'), None) if letter.is_alphabetic())
+            }
+            WordPattern::Any => true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    Custom(&'static str),
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Custom(ending) => ending,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub alignment: Alignment,
+    pub last_line: LastLineMode,                // Применяется к последней строке только при выравнивании по ширине
+    pub break_algorithm: BreakAlgorithm,
+    pub gap_strategy: GapStrategy,              // Отсчитывается от конца строки при Direction::Rtl
+    pub avoid_rivers: bool,                     // Лишние пробелы раздаются так, чтобы промежутки соседних строк не совпадали
+    pub avoid_widows: bool,                     // Последняя строка абзаца из одного слова забирает слово из предыдущей
+    pub keep_pairs: Vec<(WordPattern, WordPattern)>,             // Соседние слова, между которыми строка не переносится ("5" и "kg", "Dr." и фамилия)
+    pub keep_together: Vec<String>,             // Фразы, которые не разрываются между строками; U+2060 склеивает слова и внутри текста
+    pub direction: Direction,                   // Также базовое направление абзацев при bidi
+    pub bidi: bool,                             // Слова строк переставляются в порядок вывода по алгоритму Unicode Bidi (упрощённо)
+    pub kashida: bool,                          // Выравнивание по ширине строк с арабскими словами удлиняет соединения букв (U+0640), а не промежутки
+    pub cjk: bool,                              // Строки переносятся между символами CJK с учётом кинсоку, выравнивание растягивает промежутки между ними
+    pub segmenter: Option<Arc<dyn Segmenter + Send + Sync>>,     // Границы слов в тексте без пробелов; переносятся и растягиваются так же, как символы CJK
+    pub break_at_hyphens: bool,                 // Слова переносятся и после своих дефисов и тире, без добавления знака переноса
+    pub keep_urls: bool,                        // Адреса и пути не переносятся после "/", "?", "&" и "." - слишком длинные выходят за ширину строки
+    pub splitters: Vec<Arc<dyn WordSplitter + Send + Sync>>,     // Дополнительные места разрыва слов сверх переносов, дефисов и адресов
+    pub tokenizer: Option<Arc<dyn Tokenizer + Send + Sync>>,     // Деление текста на слова; по умолчанию - по пробелам (WhitespaceTokenizer)
+    pub overflow: OverflowPolicy,
+    pub measure: Arc<dyn Measure + Send + Sync>,                  // Ширина слов; по умолчанию - число символов (WidthMode::Chars)
+    pub ignore_ansi: bool,                      // Управляющие последовательности ANSI не занимают места, но сохраняются в выводе
+    pub newlines: NewlinePolicy,
+    pub tabs: TabPolicy,
+    pub paragraph_separator: String,            // Вставляется между абзацами после перевода строки; по умолчанию - пустая строка
+    pub line_ending: LineEnding,                // Переводы строк вывода, в том числе внутри paragraph_separator
+    pub ensure_trailing_newline: bool,          // Завершать непустой вывод переводом строки
+    pub max_lines: Option<usize>,               // Выводится не больше строк текста (без разделителей абзацев); последняя заканчивается ellipsis
+    pub ellipsis: String,
+    pub hyphen: String,                         // Пишется в конце строки при переносе слова: "-", "\u{2010}" или ничего; его ширина входит в ширину строки
+    pub markdown: bool,                         // Блоки кода с оградой, заголовки и таблицы Markdown выводятся как есть
+    pub preformatted: Option<usize>,            // Строки, начинающиеся с этого числа пробелов или с табуляции, выводятся как есть (как у fmt -p)
+    pub list_items: bool,                       // Строки с маркером списка ("- ", "1. ") начинают пункт; продолжение выравнивается под текстом пункта
+    pub prefix: String,                         // Снимается с каждой входной строки и пишется перед каждой выходной (например, "> " в цитатах)
+    pub initial_indent: String,                 // Пишется перед первой строкой каждого абзаца; ширина строки уменьшается на его ширину
+    pub subsequent_indent: String,              // Пишется перед остальными строками абзаца
+    pub strip_control: bool,                    // Управляющие символы (кроме пробельных и ESC при ignore_ansi) удаляются из вывода
+    pub stretch_limit: usize,                   // Промежутки шире этого числа пробелов отмечаются в Document::warnings
+    pub locale: Locale,
+    pub hanging_punctuation: bool,              // Запятая, точка, дефис и кавычка в конце строки выступают за правый край и не занимают её ширину
+    pub sentence_spacing: bool,                 // После ".", "?" и "!" в конце слова промежуток на пробел шире; лишние пробелы достаются ему первым
+    pub min_gap: usize,                         // Ширина промежутка между словами в пробелах без растяжения; строки разбиваются с её учётом
+    pub collapse_whitespace: bool,              // Несколько пробелов подряд - один промежуток; иначе при выравнивании не по ширине промежуток внутри входной строки сохраняет свои пробелы
+    pub max_gap: Option<usize>,                 // Строки, которым при выравнивании по ширине понадобились бы более широкие промежутки, выравниваются влево
+    pub letter_spacing: Option<usize>,          // Наибольшая ширина промежутка при выравнивании; остаток раскладывается между буквами
+    pub pad_short_lines: bool,                  // Дополнять ли короткие строки пробелами до ширины справа
+    pub fill: char,                             // Символ промежутков и отступов; ширина промежутков всё равно считается в пробелах
+    #[cfg(feature = "hyphenation")]
+    pub hyphenator: Option<Arc<Hyphenator>>,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            alignment: Alignment::Justify,
+            last_line: LastLineMode::Justify,
+            break_algorithm: BreakAlgorithm::Greedy,
+            gap_strategy: GapStrategy::LeftBiased,
+            avoid_rivers: false,
+            avoid_widows: false,
+            keep_pairs: Vec::new(),
+            keep_together: Vec::new(),
+            direction: Direction::Ltr,
+            bidi: false,
+            kashida: false,
+            cjk: false,
+            break_at_hyphens: false,
+            keep_urls: false,
+            splitters: Vec::new(),
+            tokenizer: None,
+            segmenter: None,
+            overflow: OverflowPolicy::Error,
+            measure: Arc::new(WidthMode::Chars),
+            ignore_ansi: false,
+            newlines: NewlinePolicy::ParagraphOnly,
+            tabs: TabPolicy::TreatAsSpace,
+            paragraph_separator: String::from("\n"),
+            line_ending: LineEnding::Lf,
+            ensure_trailing_newline: false,
+            max_lines: None,
+            ellipsis: String::from("\u{2026}"),
+            hyphen: String::from("-"),
+            markdown: false,
+            preformatted: None,
+            list_items: false,
+            prefix: String::new(),
+            initial_indent: String::new(),
+            subsequent_indent: String::new(),
+            strip_control: false,
+            stretch_limit: 4,
+            locale: Locale::Default,
+            hanging_punctuation: false,
+            sentence_spacing: false,
+            min_gap: 1,
+            collapse_whitespace: true,
+            max_gap: None,
+            letter_spacing: None,
+            pad_short_lines: true,
+            fill: ' ',
+            #[cfg(feature = "hyphenation")]
+            hyphenator: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Formatter {
+    line_width: usize,
+    options: Options,
+}
+
+impl Formatter {
+    pub fn new(line_width: usize) -> Formatter {
+        Formatter {
+            line_width,
+            options: Options::default(),
+        }
+    }
+    pub fn with_options(line_width: usize, options: Options) -> Formatter {
+        Formatter {
+            line_width,
+            options,
+        }
+    }
+    pub fn alignment(mut self, alignment: Alignment) -> Formatter {
+        self.options.alignment = alignment;
+        self
+    }
+    pub fn last_line(mut self, last_line: LastLineMode) -> Formatter {
+        self.options.last_line = last_line;
+        self
+    }
+    pub fn break_algorithm(mut self, break_algorithm: BreakAlgorithm) -> Formatter {
+        self.options.break_algorithm = break_algorithm;
+        self
+    }
+    pub fn gap_strategy(mut self, gap_strategy: GapStrategy) -> Formatter {
+        self.options.gap_strategy = gap_strategy;
+        self
+    }
+    pub fn avoid_rivers(mut self, avoid_rivers: bool) -> Formatter {
+        self.options.avoid_rivers = avoid_rivers;
+        self
+    }
+    pub fn avoid_widows(mut self, avoid_widows: bool) -> Formatter {
+        self.options.avoid_widows = avoid_widows;
+        self
+    }
+    pub fn keep_pair(mut self, first: WordPattern, second: WordPattern) -> Formatter {   // Добавляет правило к Options::keep_pairs
+        self.options.keep_pairs.push((first, second));
+        self
+    }
+    pub fn keep_together(mut self, phrases: &[&str]) -> Formatter {   // Добавляет фразы к Options::keep_together
+        self.options.keep_together.extend(phrases.iter().map(|phrase| String::from(*phrase)));
+        self
+    }
+    pub fn direction(mut self, direction: Direction) -> Formatter {
+        self.options.direction = direction;
+        self
+    }
+    pub fn bidi(mut self, bidi: bool) -> Formatter {
+        self.options.bidi = bidi;
+        self
+    }
+    pub fn kashida(mut self, kashida: bool) -> Formatter {
+        self.options.kashida = kashida;
+        self
+    }
+    pub fn cjk(mut self, cjk: bool) -> Formatter {
+        self.options.cjk = cjk;
+        self
+    }
+    pub fn segmenter<S: Segmenter + Send + Sync + 'static>(mut self, segmenter: S) -> Formatter {
+        self.options.segmenter = Some(Arc::new(segmenter));
+        self
+    }
+    pub fn break_at_hyphens(mut self, break_at_hyphens: bool) -> Formatter {
+        self.options.break_at_hyphens = break_at_hyphens;
+        self
+    }
+    pub fn keep_urls(mut self, keep_urls: bool) -> Formatter {
+        self.options.keep_urls = keep_urls;
+        self
+    }
+    pub fn splitter<S: WordSplitter + Send + Sync + 'static>(mut self, splitter: S) -> Formatter {   // Добавляет правило к Options::splitters
+        self.options.splitters.push(Arc::new(splitter));
+        self
+    }
+    pub fn tokenizer<T: Tokenizer + Send + Sync + 'static>(mut self, tokenizer: T) -> Formatter {
+        self.options.tokenizer = Some(Arc::new(tokenizer));
+        self
+    }
+    pub fn overflow(mut self, overflow: OverflowPolicy) -> Formatter {
+        self.options.overflow = overflow;
+        self
+    }
+    pub fn width_mode(mut self, width_mode: WidthMode) -> Formatter {
+        self.options.measure = Arc::new(width_mode);
+        self
+    }
+    pub fn measure<M: Measure + Send + Sync + 'static>(mut self, measure: M) -> Formatter {
+        self.options.measure = Arc::new(measure);
+        self
+    }
+    pub fn ignore_ansi(mut self, ignore_ansi: bool) -> Formatter {
+        self.options.ignore_ansi = ignore_ansi;
+        self
+    }
+    pub fn newlines(mut self, newlines: NewlinePolicy) -> Formatter {
+        self.options.newlines = newlines;
+        self
+    }
+    pub fn tabs(mut self, tabs: TabPolicy) -> Formatter {
+        self.options.tabs = tabs;
+        self
+    }
+    pub fn line_ending(mut self, line_ending: LineEnding) -> Formatter {
+        self.options.line_ending = line_ending;
+        self
+    }
+    pub fn ensure_trailing_newline(mut self, ensure_trailing_newline: bool) -> Formatter {
+        self.options.ensure_trailing_newline = ensure_trailing_newline;
+        self
+    }
+    pub fn max_lines(mut self, max_lines: usize) -> Formatter {
+        self.options.max_lines = Some(max_lines);
+        self
+    }
+    pub fn ellipsis<S: Into<String>>(mut self, ellipsis: S) -> Formatter {
+        self.options.ellipsis = ellipsis.into();
+        self
+    }
+    pub fn hyphen<S: Into<String>>(mut self, hyphen: S) -> Formatter {
+        self.options.hyphen = hyphen.into();
+        self
+    }
+    pub fn markdown(mut self, markdown: bool) -> Formatter {
+        self.options.markdown = markdown;
+        self
+    }
+    pub fn preformatted(mut self, indent: usize) -> Formatter {
+        self.options.preformatted = Some(indent);
+        self
+    }
+    pub fn list_items(mut self, list_items: bool) -> Formatter {
+        self.options.list_items = list_items;
+        self
+    }
+    pub fn prefix<S: Into<String>>(mut self, prefix: S) -> Formatter {
+        self.options.prefix = prefix.into();
+        self
+    }
+    pub fn initial_indent<S: Into<String>>(mut self, initial_indent: S) -> Formatter {
+        self.options.initial_indent = initial_indent.into();
+        self
+    }
+    pub fn subsequent_indent<S: Into<String>>(mut self, subsequent_indent: S) -> Formatter {
+        self.options.subsequent_indent = subsequent_indent.into();
+        self
+    }
+    pub fn first_line_indent(mut self, columns: usize) -> Formatter {               // Отступ первой строки абзаца в пробелах
+        self.options.initial_indent = " ".repeat(columns);
+        self
+    }
+    pub fn strip_control(mut self, strip_control: bool) -> Formatter {
+        self.options.strip_control = strip_control;
+        self
+    }
+    pub fn stretch_limit(mut self, stretch_limit: usize) -> Formatter {
+        self.options.stretch_limit = stretch_limit;
+        self
+    }
+    pub fn locale(mut self, locale: Locale) -> Formatter {
+        self.options.locale = locale;
+        self
+    }
+    pub fn hanging_punctuation(mut self, hanging_punctuation: bool) -> Formatter {
+        self.options.hanging_punctuation = hanging_punctuation;
+        self
+    }
+    pub fn sentence_spacing(mut self, sentence_spacing: bool) -> Formatter {
+        self.options.sentence_spacing = sentence_spacing;
+        self
+    }
+    pub fn min_gap(mut self, min_gap: usize) -> Formatter {
+        self.options.min_gap = min_gap;
+        self
+    }
+    pub fn collapse_whitespace(mut self, collapse_whitespace: bool) -> Formatter {
+        self.options.collapse_whitespace = collapse_whitespace;
+        self
+    }
+    pub fn max_gap(mut self, max_gap: usize) -> Formatter {
+        self.options.max_gap = Some(max_gap);
+        self
+    }
+    pub fn letter_spacing(mut self, max_gap: usize) -> Formatter {
+        self.options.letter_spacing = Some(max_gap);
+        self
+    }
+    pub fn pad_short_lines(mut self, pad_short_lines: bool) -> Formatter {
+        self.options.pad_short_lines = pad_short_lines;
+        self
+    }
+    pub fn fill(mut self, fill: char) -> Formatter {
+        self.options.fill = fill;
+        self
+    }
+    pub fn paragraph_separator<S: Into<String>>(mut self, paragraph_separator: S) -> Formatter {
+        self.options.paragraph_separator = paragraph_separator.into();
+        self
+    }
+    #[cfg(feature = "hyphenation")]
+    pub fn sample_hyphenation(mut self, enabled: bool) -> Formatter {              // Только для примеров: крошечные шаблоны Hyphenator::sample_english переносят мало слов. Для настоящего языка - hyphenator с Hyphenator::from_patterns или from_pattern_text
+        self.options.hyphenator = if enabled { Some(Arc::new(Hyphenator::sample_english())) } else { None };
+        self
+    }
+    #[cfg(feature = "hyphenation")]
+    pub fn hyphenator(mut self, hyphenator: Hyphenator) -> Formatter {
+        self.options.hyphenator = Some(Arc::new(hyphenator));
+        self
+    }
+    pub fn line_width(&self) -> usize {
+        self.line_width
+    }
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+    pub fn document<'a>(&self, input: &'a str) -> Result<Document<'a>, DocError> {
+        Document::from_str_with(input, self.line_width, self.options.clone())
+    }
+    pub fn transform(&self, input: &str) -> Result<String, DocError> {
+        self.document(input)
+            .map(|document|
+                document.format_to_string()
+            )
+    }
+    pub fn transform_comment(&self, input: &str, leader: &str) -> Result<String, DocError> {
+        transform_comment(input, self.line_width, leader, self.options.clone())
+    }
+    pub fn transform_cow<'a>(&self, input: &'a str) -> Result<Cow<'a, str>, DocError> {
+        transform_cow_with(input, self.line_width, self.options.clone())
+    }
+}
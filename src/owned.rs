@@ -0,0 +1,180 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+use crate::{Block, Continuation, DocError, Document, Line, LineBreak, Options};
+
+type Piece = (usize, Range<usize>);                                                 // Номер куска текста и байты слова в нём
+
+fn piece(index: usize, source: &str, word: &str) -> Piece {
+    let start: usize = word.as_ptr() as usize - source.as_ptr() as usize;
+    (index, start..start + word.len())
+}
+
+fn locate(sources: &[(usize, &str)], word: &str) -> Piece {                        // Слова строк - срезы одного из кусков текста
+    let address: usize = word.as_ptr() as usize;
+    sources.iter().enumerate()
+        .find(|(_, (_, source))| address >= source.as_ptr() as usize && address + word.len() <= source.as_ptr() as usize + source.len())
+        .map_or((0, 0..0), |(index, &(_, source))| piece(index, source, word))
+}
+
+pub struct DocumentOwned {                                                          // Document, владеющий своим текстом: его можно хранить без исходного буфера
+    sources: Vec<(usize, String)>,
+    tokens: Vec<Piece>,
+    blocks: Vec<Block>,
+    words: Vec<Piece>,
+    lines: Vec<Line>,
+    line_widths: Vec<usize>,
+    space_width: usize,
+    options: Options,
+    sealed: bool,
+    continuation: Continuation,
+}
+
+impl DocumentOwned {
+    pub fn from_str_with(input: &str, line_width: usize, options: Options) -> Result<DocumentOwned, DocError> {
+        Document::from_str_with(input, line_width, options).map(|document| DocumentOwned::from(&document))
+    }
+    pub fn as_document(&self) -> Document<'_> {                                    // Document, ссылающийся на текст этого документа
+        let piece = |&(source, ref range): &Piece| &self.sources[source].1[range.clone()];
+        Document {
+            tokens: self.tokens.iter().map(piece).collect(),
+            sources: self.sources.iter().map(|(first_token, source)| (*first_token, source.as_str())).collect(),
+            blocks: self.blocks.clone(),
+            words: self.words.iter().map(piece).collect(),
+            lines: self.lines.clone(),
+            line_widths: self.line_widths.clone(),
+            space_width: self.space_width,
+            options: self.options.clone(),
+            sealed: self.sealed,
+            continuation: self.continuation,
+        }
+    }
+    pub fn new(line_width: usize, options: Options) -> DocumentOwned {            // Пустой документ для построения по кускам потока через push_text
+        DocumentOwned::from(&Document::new(line_width, options))
+    }
+    pub fn push_text(&mut self, text: &str) -> Result<(), DocError> {              // Как Document::push_text, но куски - части одного потока: "hel" + "lo" дают слово "hello"
+        let block: usize = if self.sealed { self.blocks.len() } else { self.blocks.len().saturating_sub(1) };   // Переразбиваем только открытый абзац
+        let first_token: usize = self.blocks.get(block).map_or(self.tokens.len(), |block| block.tokens.start);
+        let first_source: usize = self.tokens.get(first_token).map_or(self.sources.len(), |&(source, _)| source);
+        let first_line: usize = match block.checked_sub(1) {                        // Как в Document::append: строки блока кончаются разрывом не по ширине
+            Some(previous_block) => self.lines.iter().enumerate()
+                .filter(|(_, line)| line.end != LineBreak::Wrap)
+                .nth(previous_block)
+                .map_or(self.lines.len(), |(index, _)| index + 1),
+            None => 0,
+        };
+        let word_offset: usize = self.lines.get(first_line).map_or(self.words.len(), |line| line.words.start);
+
+        let last_source: usize = self.sources.len().wrapping_sub(1);
+        let retold: Option<(usize, usize)> = match self.blocks.get(block) {          // Последний кусок с начала строки открытого абзаца разбираем заново вместе с новым текстом
+            Some(open) if !open.verbatim && !self.sources.is_empty() => {
+                let token: usize = open.tokens.start.max(self.sources[last_source].0);
+                self.tokens.get(token).filter(|&&(source, _)| source == last_source && token < open.tokens.end).map(|(_, range)| {
+                    let previous: usize = token.checked_sub(1).map(|previous| &self.tokens[previous]).filter(|&&(source, _)| source == last_source).map_or(0, |(_, range)| range.end);
+                    (token, self.sources[last_source].1[..range.start].rfind('\n').map_or(0, |newline| newline + 1).max(previous))
+                })
+            }
+            _ => None,                                                              // Иначе кусок продолжает абзац, как в Document::push_text
+        };
+        let kept: usize = retold.map_or(self.tokens.len(), |(token, _)| token);     // Слова до заново разбираемого хвоста
+        let chunk: String = [retold.map_or("", |(_, start)| &self.sources[last_source].1[start..]), text].concat();
+        let replaced: bool = retold.is_some() && (kept == 0 || self.tokens[kept - 1].0 != last_source);   // На прошлый кусок больше не ссылается ни одно слово
+
+        let text_of = |&(source, ref range): &Piece| &self.sources[source].1[range.clone()];
+        let tokens: Vec<&str> = self.tokens[first_token..kept].iter().map(text_of).collect();
+        let mut blocks: Vec<Block> = self.blocks[block..].iter().map(|block| Block { tokens: block.tokens.start - first_token..block.tokens.end.min(kept) - first_token, ..block.clone() }).collect();
+        let mut sealed: bool = self.sealed;
+        if blocks.last().is_some_and(|last| last.tokens.is_empty()) {              // Весь открытый абзац разбирается заново
+            blocks.pop();
+            sealed = true;
+        }
+        let mut document: Document = Document {
+            tokens,
+            sources: self.sources[first_source..].iter().map(|(first, source)| (first.saturating_sub(first_token), source.as_str())).collect(),
+            blocks,
+            words: self.words[word_offset..].iter().map(text_of).collect(),
+            lines: self.lines[first_line..].iter().map(|line| Line { words: line.words.start - word_offset..line.words.end - word_offset, ..line.clone() }).collect(),
+            line_widths: self.line_widths[first_line.min(self.line_widths.len() - 1)..].to_vec(),
+            space_width: self.space_width,
+            options: self.options.clone(),
+            sealed,
+            continuation: self.continuation,
+        };
+        document.push_text(&chunk).map_err(|error| error.shifted(0, first_token))?;
+
+        let chunk_source: usize = if replaced { last_source } else { self.sources.len() };
+        let located = |word: &&str| {                                               // Куски документа - прежние с first_source и новый в конце
+            let (source, range): Piece = locate(&document.sources, word);
+            (if source + first_source == self.sources.len() { chunk_source } else { source + first_source }, range)
+        };
+        let tokens: Vec<Piece> = document.tokens.iter().map(located).collect();
+        let words: Vec<Piece> = document.words.iter().map(located).collect();
+        let pushed: Option<usize> = document.sources.get(self.sources.len() - first_source).map(|&(first, _)| first + first_token);   // Кусок без слов не сохраняется
+        self.tokens.truncate(first_token);
+        self.tokens.extend(tokens);
+        self.blocks.truncate(block);
+        self.blocks.extend(document.blocks.iter().map(|block| Block { tokens: block.tokens.start + first_token..block.tokens.end + first_token, ..block.clone() }));
+        self.words.truncate(word_offset);
+        self.words.extend(words);
+        self.lines.truncate(first_line);
+        self.lines.extend(document.lines.iter().map(|line| Line { words: line.words.start + word_offset..line.words.end + word_offset, ..line.clone() }));
+        self.sealed = document.sealed;
+        drop(document);
+        if let Some(first) = pushed {
+            self.sources.truncate(chunk_source);
+            self.sources.push((first, chunk));
+        }
+        Ok(())
+    }
+    pub fn end_paragraph(&mut self) {
+        self.sealed = true;
+    }
+    pub fn line_width(&self) -> usize {
+        self.line_widths[0]
+    }
+    pub fn format_to_string(&self) -> String {
+        self.as_document().format_to_string()
+    }
+}
+
+impl From<&Document<'_>> for DocumentOwned {
+    fn from(document: &Document<'_>) -> DocumentOwned {
+        DocumentOwned {
+            sources: document.sources.iter().map(|(first_token, source)| (*first_token, source.to_string())).collect(),
+            tokens: document.tokens.iter().enumerate()
+                .map(|(token, word)| {                                              // Слово берём из того же куска, что и token_range
+                    let index: usize = document.sources.iter().rposition(|&(first_token, _)| first_token <= token).unwrap_or(0);
+                    piece(index, document.sources[index].1, word)
+                })
+                .collect(),
+            blocks: document.blocks.clone(),
+            words: document.words.iter().map(|word| locate(&document.sources, word)).collect(),
+            lines: document.lines.clone(),
+            line_widths: document.line_widths.clone(),
+            space_width: document.space_width,
+            options: document.options.clone(),
+            sealed: document.sealed,
+            continuation: document.continuation,
+        }
+    }
+}
+
+impl From<Document<'_>> for DocumentOwned {
+    fn from(document: Document<'_>) -> DocumentOwned {
+        DocumentOwned::from(&document)
+    }
+}
+
+impl<'a> From<&'a DocumentOwned> for Document<'a> {
+    fn from(document: &'a DocumentOwned) -> Document<'a> {
+        document.as_document()
+    }
+}
+
+impl fmt::Display for DocumentOwned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_document().fmt(f)
+    }
+}
@@ -0,0 +1,7 @@
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Quality {                                                                // Насколько ровно набран документ, см. Document::quality
+    pub max_gap: usize,                         // Самый широкий промежуток между словами в пробелах
+    pub gap_variance: f64,                      // Дисперсия ширин всех промежутков в пробелах; 0 - все одинаковые
+    pub overstretched_lines: usize,             // Строки с промежутками шире Options::stretch_limit
+    pub last_line_fill: f64,                    // Доля ширины, занятая текстом последней строки: от 0 до 1
+}
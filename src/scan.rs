@@ -0,0 +1,77 @@
+use crate::is_breaking_whitespace;
+
+const LOW_BITS: u64 = 0x0101_0101_0101_0101;
+const HIGH_BITS: u64 = 0x8080_8080_8080_8080;
+
+fn is_ascii_whitespace(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\x0B' | b'\x0C' | b'\r')                // Те же символы, что и у char::is_whitespace в диапазоне ASCII
+}
+
+fn plain_chunk(chunk: &[u8]) -> bool {                                              // В восьми байтах нет ни управляющих символов с пробелом, ни не-ASCII
+    let word: u64 = u64::from_le_bytes(chunk.try_into().expect("срез ровно из 8 байт"));
+    let below_space: u64 = word.wrapping_sub(LOW_BITS * 0x21) & !word & HIGH_BITS;
+    below_space == 0 && word & HIGH_BITS == 0
+}
+
+fn separator_at(input: &str, index: usize) -> Option<usize> {                      // Длина разрывающего пробела, начинающегося с index
+    let byte: u8 = input.as_bytes()[index];
+    if byte < 0x80 {
+        return is_ascii_whitespace(byte).then_some(1);
+    }
+    let c: char = input[index..].chars().next().expect("index на границе символа");
+    is_breaking_whitespace(c).then(|| c.len_utf8())
+}
+
+fn next_char(input: &str, index: usize) -> usize {
+    let byte: u8 = input.as_bytes()[index];
+    if byte < 0x80 {
+        1
+    } else {
+        input[index..].chars().next().map_or(1, char::len_utf8)
+    }
+}
+
+pub(crate) struct Words<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Words<'a> {
+    pub(crate) fn new(input: &'a str) -> Words<'a> {
+        Words { input, position: 0 }
+    }
+    fn word_end(&self, mut index: usize) -> usize {
+        let bytes: &[u8] = self.input.as_bytes();
+        loop {
+            while index + 8 <= bytes.len() && plain_chunk(&bytes[index..index + 8]) {   // Обычные буквы пропускаем по восемь байт за раз
+                index += 8;
+            }
+            if index >= bytes.len() {
+                return bytes.len();
+            }
+            if separator_at(self.input, index).is_some() {
+                return index;
+            }
+            index += next_char(self.input, index);
+        }
+    }
+}
+
+impl<'a> Iterator for Words<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        while self.position < self.input.len() {
+            match separator_at(self.input, self.position) {
+                Some(length) => self.position += length,
+                None => break,
+            }
+        }
+        if self.position >= self.input.len() {
+            return None;
+        }
+        let start: usize = self.position;
+        self.position = self.word_end(start);
+        Some(&self.input[start..self.position])
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(feature = "dictionary")]
+use alloc::collections::BTreeSet;
+#[cfg(feature = "dictionary")]
+use alloc::string::String;
+#[cfg(feature = "dictionary")]
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+pub trait Segmenter {                                                               // Границы слов внутри текста без пробелов (тайский, лаосский, кхмерский)
+    fn boundaries(&self, text: &str) -> Vec<usize>;                                 // Байты строго внутри text, где начинается следующее слово
+}
+
+impl fmt::Debug for dyn Segmenter + Send + Sync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Segmenter")
+    }
+}
+
+#[cfg(feature = "dictionary")]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DictionarySegmenter {                                                    // Разбиение на наименьшее число слов словаря
+    words: BTreeSet<String>,
+    longest_word: usize,                        // В символах
+}
+
+#[cfg(feature = "dictionary")]
+impl DictionarySegmenter {
+    pub fn new<I: IntoIterator<Item = S>, S: Into<String>>(words: I) -> DictionarySegmenter {
+        let words: BTreeSet<String> = words.into_iter().map(Into::into).filter(|word| !word.is_empty()).collect();
+        let longest_word: usize = words.iter().map(|word| word.chars().count()).max().unwrap_or(0);
+        DictionarySegmenter { words, longest_word }
+    }
+}
+
+#[cfg(feature = "dictionary")]
+impl Segmenter for DictionarySegmenter {
+    fn boundaries(&self, text: &str) -> Vec<usize> {
+        let offsets: Vec<usize> = text.char_indices().map(|(index, _)| index).chain(Some(text.len())).collect();
+        let char_count: usize = offsets.len() - 1;
+        let mut best: Vec<Option<(usize, usize, bool)>> = vec![None; char_count + 1];   // best[j] - (стоимость, начало последнего слова, есть ли оно в словаре) для первых j символов
+        best[0] = Some((0, 0, true));
+        for end in 1..=char_count {
+            for start in end.saturating_sub(self.longest_word.max(1))..end {
+                let Some((cost, _, _)) = best[start] else {
+                    continue;
+                };
+                let known: bool = self.words.contains(&text[offsets[start]..offsets[end]]);
+                if !known && end - start > 1 {
+                    continue;
+                }
+                let cost: usize = cost + if known { 1 } else { 2 };                  // Незнакомый символ - отдельное слово, но дороже словарного
+                if best[end].is_none_or(|(best, _, _)| cost < best) {
+                    best[end] = Some((cost, start, known));
+                }
+            }
+        }
+
+        let mut boundaries: Vec<usize> = Vec::new();
+        let mut end: usize = char_count;
+        while let Some((_, start, known)) = best[end].filter(|_| end > 0) {
+            let previous_known: bool = best[start].is_some_and(|(_, _, known)| known);
+            if start > 0 && (known || previous_known) {                             // Подряд идущие незнакомые символы остаются одним словом
+                boundaries.push(offsets[start]);
+            }
+            end = start;
+        }
+        boundaries.reverse();
+        boundaries
+    }
+}
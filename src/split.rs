@@ -0,0 +1,71 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::width::Graphemes;
+
+pub trait WordSplitter {                                                            // Допустимые места разрыва строки внутри слова
+    fn split_points(&self, word: &str) -> Vec<(usize, bool)>;                       // (байт строго внутри word, добавляется ли знак переноса)
+}
+
+impl fmt::Debug for dyn WordSplitter + Send + Sync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("WordSplitter")
+    }
+}
+
+const DASHES: &[char] = &['-', '\u{2010}', '\u{2013}', '\u{2014}'];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct HyphenSplitter;                                                          // После дефисов и тире между буквами или цифрами: "state-|of-|the-|art"
+
+impl WordSplitter for HyphenSplitter {
+    fn split_points(&self, word: &str) -> Vec<(usize, bool)> {
+        word.char_indices()
+            .filter(|&(index, c)| {
+                let after: usize = index + c.len_utf8();
+                DASHES.contains(&c)
+                    && word[..index].chars().next_back().is_some_and(char::is_alphanumeric)
+                    && word[after..].chars().next().is_some_and(char::is_alphanumeric)
+            })
+            .map(|(index, c)| (index + c.len_utf8(), false))
+            .collect()
+    }
+}
+
+const URL_SEPARATORS: &[char] = &['/', '?', '&', '.'];
+
+pub(crate) fn is_url(word: &str) -> bool {                                          // Адрес или путь: "https://...", "www....", "/usr/...", "~/..."
+    word.contains("://") || word.starts_with("www.") || (word.starts_with(['/', '~', '.']) && word[1..].contains('/'))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct UrlSplitter;                                                             // После разделителей адреса, если за ними не идёт ещё один; другие слова не делятся
+
+impl WordSplitter for UrlSplitter {
+    fn split_points(&self, word: &str) -> Vec<(usize, bool)> {
+        if !is_url(word) {
+            return Vec::new();
+        }
+        word.match_indices(URL_SEPARATORS)
+            .map(|(index, separator)| index + separator.len())
+            .filter(|&point| point < word.len() && !word[point..].starts_with(URL_SEPARATORS))
+            .map(|point| (point, false))
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CharSplitter;                                                            // Между любыми кластерами графем; так режет слова OverflowPolicy::BreakAnywhere
+
+impl WordSplitter for CharSplitter {
+    fn split_points(&self, word: &str) -> Vec<(usize, bool)> {
+        let mut offset: usize = 0;
+        Graphemes::new(word)
+            .map(|cluster| {
+                offset += cluster.len();
+                (offset, false)
+            })
+            .filter(|&(point, _)| point < word.len())
+            .collect()
+    }
+}
@@ -0,0 +1,21 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+pub trait Tokenizer {                                                               // Деление текста блока на слова; внутри слова строка не переносится по пробелам
+    fn words<'a>(&self, text: &'a str) -> Vec<&'a str>;                             // Непустые срезы text по порядку
+}
+
+impl fmt::Debug for dyn Tokenizer + Send + Sync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Tokenizer")
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct WhitespaceTokenizer;                                                     // По разрывающим пробелам; так делится текст без Options::tokenizer
+
+impl Tokenizer for WhitespaceTokenizer {
+    fn words<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        crate::words(text).collect()
+    }
+}
@@ -0,0 +1,100 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::width;
+use crate::Options;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub enum Violation {
+    LineTooWide {                               // Строка шире заданной ширины
+        line: usize,
+        width: usize,
+    },
+    LineTooShort {                              // Непустая строка уже заданной ширины, хотя Options::pad_short_lines
+        line: usize,
+        width: usize,
+    },
+    UnevenGaps {                                // Промежутки строки различаются больше чем на пробел
+        line: usize,
+        narrowest: usize,
+        widest: usize,
+    },
+    TextChanged {                               // С этой строки текст без пробелов и переносов расходится с исходным
+        line: usize,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::LineTooWide { line, width } => write!(f, "line {} is {} wide, more than the line width", line + 1, width),
+            Violation::LineTooShort { line, width } => write!(f, "line {} is {} wide, less than the line width", line + 1, width),
+            Violation::UnevenGaps { line, narrowest, widest } => write!(f, "line {} has gaps from {} to {} spaces", line + 1, narrowest, widest),
+            Violation::TextChanged { line } => write!(f, "line {} does not match the input text", line + 1),
+        }
+    }
+}
+
+fn is_gap(c: char, options: &Options) -> bool {
+    c == ' ' || c == options.fill
+}
+
+fn letters<'t>(text: &'t str, options: &'t Options) -> impl Iterator<Item = char> + 't {   // Символы без пробелов, дефисов и мягких переносов: их расставляет разбиение
+    text.chars().filter(move |&c| !c.is_whitespace() && !is_gap(c, options) && c != '-' && c != '\u{AD}' && !options.hyphen.contains(c))
+}
+
+fn gaps(line: &str, options: &Options) -> Vec<usize> {                             // Ширины промежутков между словами в пробелах, без отступов по краям
+    let space_width: usize = width::text_width(" ", options).max(1);
+    let mut gaps: Vec<usize> = Vec::new();
+    let mut rest: &str = line.trim_matches(|c| is_gap(c, options));
+    while let Some(start) = rest.find(|c| is_gap(c, options)) {
+        let end: usize = rest[start..].find(|c| !is_gap(c, options)).map_or(rest.len(), |length| start + length);
+        gaps.push(width::text_width(&rest[start..end], options) / space_width);
+        rest = &rest[end..];
+    }
+    gaps
+}
+
+pub fn verify(input: &str, output: &str, line_width: usize, options: &Options) -> Vec<Violation> {   // Проверяет вывод на ширину строк, ровность промежутков и совпадение текста с input
+    let mut violations: Vec<Violation> = Vec::new();
+    let separators: Vec<&str> = options.paragraph_separator.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+    let expected: Vec<char> = input.lines().flat_map(|line| letters(crate::line_body(line, options), options)).collect();
+    let mut matched: usize = 0;                                                     // Сколько символов input уже встретилось в выводе
+    let mut text_changed: bool = false;
+    let lines: Vec<&str> = output.split(options.line_ending.as_str()).collect();
+    for (number, line) in lines.iter().enumerate() {
+        let line: &str = line.trim_end_matches(['\r', '\n']);
+        let width: usize = width::text_width(line, options);
+        if width > line_width {
+            violations.push(Violation::LineTooWide { line: number, width });
+        } else if width < line_width && options.pad_short_lines && !line.trim().is_empty() {
+            violations.push(Violation::LineTooShort { line: number, width });
+        }
+        let gaps: Vec<usize> = gaps(line, options);
+        if let (Some(&narrowest), Some(&widest)) = (gaps.iter().min(), gaps.iter().max()) {
+            if widest > narrowest + 1 {
+                violations.push(Violation::UnevenGaps { line: number, narrowest, widest });
+            }
+        }
+
+        if text_changed || separators.contains(&line.trim()) {
+            continue;
+        }
+        let mut body: &str = crate::line_body(line, options);
+        if number + 1 == lines.len() && options.max_lines.is_some() {              // Оборванный текст заканчивается многоточием
+            body = body.trim_end_matches(|c| is_gap(c, options)).trim_end_matches(options.ellipsis.as_str());
+        }
+        let written: Vec<char> = letters(body, options).collect();
+        if expected[matched..].starts_with(&written) {
+            matched += written.len();
+        } else {
+            violations.push(Violation::TextChanged { line: number });
+            text_changed = true;
+        }
+    }
+    if !text_changed && matched < expected.len() && options.max_lines.is_none() {
+        violations.push(Violation::TextChanged { line: lines.len() - 1 });
+    }
+    violations
+}
@@ -0,0 +1,36 @@
+use alloc::string::String;
+use core::fmt;
+use core::ops::Range;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub enum Warning {
+    WordTruncated {                             // Слово шире строки обрезано (OverflowPolicy::Truncate)
+        word: String,
+        range: Range<usize>,
+        word_index: usize,
+    },
+    LineOverflow {                              // Строка шире заданной ширины (OverflowPolicy::Overflow)
+        line: usize,
+    },
+    ControlStripped {                           // Управляющий символ удалён из вывода (Options::strip_control)
+        character: char,
+        offset: usize,                          // Байтовое смещение символа во входном тексте
+    },
+    LineOverstretched {                         // Промежуток между словами шире Options::stretch_limit пробелов
+        line: usize,
+        gap: usize,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::WordTruncated { word, range, .. } =>
+                write!(f, "word {:?} at bytes {}..{} was truncated to fit the line width", word, range.start, range.end),
+            Warning::LineOverflow { line } => write!(f, "line {} is wider than the line width", line + 1),
+            Warning::ControlStripped { character, offset } => write!(f, "control character {:?} at byte {} was removed", character, offset),
+            Warning::LineOverstretched { line, gap } => write!(f, "line {} is stretched to gaps of {} spaces", line + 1, gap),
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+use crate::{is_hidden, Options, SOFT_HYPHEN};
+
+pub trait Measure {
+    fn width(&self, text: &str) -> usize;
+}
+
+impl fmt::Debug for dyn Measure + Send + Sync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Measure")
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WidthMode {
+    Bytes,                                      // Длина в байтах Utf-8
+    Chars,                                      // Каждый символ занимает одну позицию
+    Columns,                                    // Ширина в колонках терминала: широкие символы Восточной Азии - 2, комбинируемые - 0
+    #[cfg(feature = "graphemes")]
+    Graphemes,                                  // Каждый кластер графем (буква с диакритикой, эмодзи-последовательность) занимает одну позицию
+}
+
+impl Measure for WidthMode {
+    fn width(&self, text: &str) -> usize {
+        match self {
+            #[cfg(feature = "graphemes")]
+            WidthMode::Graphemes => Graphemes::new(text).filter(|cluster| cluster.chars().any(|c| c != SOFT_HYPHEN && !is_zero_width(c))).count(),
+            _ => text.chars().map(|c| char_width(c, *self)).sum(),
+        }
+    }
+}
+
+pub(crate) struct Measured {                                                        // Заранее измеренные слова; прочий текст (промежутки, части перенесённых слов) меряет fallback
+    widths: BTreeMap<String, usize>,
+    fallback: Arc<dyn Measure + Send + Sync>,
+}
+
+impl Measured {
+    pub(crate) fn new(widths: BTreeMap<String, usize>, fallback: Arc<dyn Measure + Send + Sync>) -> Measured {
+        Measured { widths, fallback }
+    }
+}
+
+impl Measure for Measured {
+    fn width(&self, text: &str) -> usize {
+        self.widths.get(text).copied().unwrap_or_else(|| self.fallback.width(text))
+    }
+}
+
+const ZERO_WIDTH: &[(u32, u32)] = &[
+    (0x0300, 0x036F), (0x0483, 0x0489), (0x0591, 0x05BD), (0x05BF, 0x05BF), (0x05C1, 0x05C2),
+    (0x05C4, 0x05C5), (0x05C7, 0x05C7), (0x0610, 0x061A), (0x064B, 0x065F), (0x0670, 0x0670),
+    (0x06D6, 0x06DC), (0x06DF, 0x06E4), (0x06E7, 0x06E8), (0x06EA, 0x06ED), (0x0E31, 0x0E31),
+    (0x0E34, 0x0E3A), (0x0E47, 0x0E4E), (0x1160, 0x11FF), (0x1AB0, 0x1AFF), (0x1DC0, 0x1DFF),
+    (0x200B, 0x200F), (0x2060, 0x2064), (0x20D0, 0x20FF), (0x3099, 0x309A), (0xFE00, 0xFE0F),
+    (0xFE20, 0xFE2F), (0xFEFF, 0xFEFF), (0xE0100, 0xE01EF),
+];
+
+const WIDE: &[(u32, u32)] = &[
+    (0x1100, 0x115F), (0x231A, 0x231B), (0x2329, 0x232A), (0x23E9, 0x23EC), (0x23F0, 0x23F0),
+    (0x23F3, 0x23F3), (0x25FD, 0x25FE), (0x2614, 0x2615), (0x2648, 0x2653), (0x267F, 0x267F),
+    (0x2693, 0x2693), (0x26A1, 0x26A1), (0x26AA, 0x26AB), (0x26BD, 0x26BE), (0x26C4, 0x26C5),
+    (0x26CE, 0x26CE), (0x26D4, 0x26D4), (0x26EA, 0x26EA), (0x26F2, 0x26F3), (0x26F5, 0x26F5),
+    (0x26FA, 0x26FA), (0x26FD, 0x26FD), (0x2705, 0x2705), (0x270A, 0x270B), (0x2728, 0x2728),
+    (0x274C, 0x274C), (0x274E, 0x274E), (0x2753, 0x2755), (0x2757, 0x2757), (0x2795, 0x2797),
+    (0x27B0, 0x27B0), (0x27BF, 0x27BF), (0x2B1B, 0x2B1C), (0x2B50, 0x2B50), (0x2B55, 0x2B55),
+    (0x2E80, 0x303E), (0x3041, 0x33FF), (0x3400, 0x4DBF), (0x4E00, 0x9FFF), (0xA000, 0xA4CF),
+    (0xA960, 0xA97F), (0xAC00, 0xD7A3), (0xF900, 0xFAFF), (0xFE10, 0xFE19), (0xFE30, 0xFE6F),
+    (0xFF00, 0xFF60), (0xFFE0, 0xFFE6), (0x16FE0, 0x16FE4), (0x17000, 0x18CFF), (0x1B000, 0x1B2FF),
+    (0x1F004, 0x1F004), (0x1F0CF, 0x1F0CF), (0x1F18E, 0x1F18E), (0x1F191, 0x1F19A), (0x1F200, 0x1F265),
+    (0x1F300, 0x1F64F), (0x1F680, 0x1F6FF), (0x1F7E0, 0x1F7EB), (0x1F90C, 0x1F9FF), (0x1FA70, 0x1FAFF),
+    (0x20000, 0x2FFFD), (0x30000, 0x3FFFD),
+];
+
+const EXTEND: &[(u32, u32)] = &[
+    (0x00AD, 0x00AD), (0x0300, 0x036F), (0x0483, 0x0489), (0x0591, 0x05BD), (0x05BF, 0x05BF),
+    (0x05C1, 0x05C2), (0x05C4, 0x05C5), (0x05C7, 0x05C7), (0x0610, 0x061A), (0x064B, 0x065F),
+    (0x0670, 0x0670), (0x06D6, 0x06DC), (0x06DF, 0x06E4), (0x06E7, 0x06E8), (0x06EA, 0x06ED),
+    (0x0900, 0x0903), (0x093A, 0x094F), (0x0951, 0x0957), (0x0962, 0x0963), (0x0E31, 0x0E31),
+    (0x0E34, 0x0E3A), (0x0E47, 0x0E4E), (0x1160, 0x11FF), (0x1AB0, 0x1AFF), (0x1DC0, 0x1DFF),
+    (0x200C, 0x200D), (0x20D0, 0x20FF), (0x3099, 0x309A), (0xFE00, 0xFE0F), (0xFE20, 0xFE2F),
+    (0x1F3FB, 0x1F3FF), (0xE0020, 0xE007F), (0xE0100, 0xE01EF),
+];
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+fn is_pictographic(c: char) -> bool {
+    ('\u{2600}'..='\u{27BF}').contains(&c) || ('\u{1F000}'..='\u{1FAFF}').contains(&c)
+}
+
+pub(crate) struct Graphemes<'a> {                                                   // Упрощённое разбиение на кластеры графем по мотивам UAX #29
+    rest: &'a str,
+}
+
+impl<'a> Graphemes<'a> {
+    pub(crate) fn new(text: &'a str) -> Graphemes<'a> {
+        Graphemes { rest: text }
+    }
+}
+
+impl<'a> Iterator for Graphemes<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let mut chars = self.rest.char_indices();
+        let (_, first) = chars.next()?;
+        let mut previous: char = first;
+        let mut regional_indicators: u32 = if is_regional_indicator(first) { 1 } else { 0 };
+        let mut end: usize = self.rest.len();
+
+        for (index, c) in chars {
+            let joins: bool =
+                in_table(EXTEND, c)
+                || (previous == ZERO_WIDTH_JOINER && is_pictographic(c))
+                || (is_regional_indicator(c) && regional_indicators % 2 == 1);
+            if !joins {
+                end = index;
+                break;
+            }
+            if is_regional_indicator(c) {
+                regional_indicators += 1;
+            }
+            previous = c;
+        }
+
+        let (cluster, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Some(cluster)
+    }
+}
+
+fn in_table(table: &[(u32, u32)], c: char) -> bool {
+    let code: u32 = c as u32;
+    table.binary_search_by(|&(first, last)|
+        if last < code {
+            core::cmp::Ordering::Less
+        } else if first > code {
+            core::cmp::Ordering::Greater
+        } else {
+            core::cmp::Ordering::Equal
+        }
+    ).is_ok()
+}
+
+pub(crate) fn is_zero_width(c: char) -> bool {                                     // Невидимые знаки форматирования: ZWSP, ZWNJ, ZWJ, WJ, BOM и селекторы вариантов
+    matches!(c, '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}' | '\u{FE00}'..='\u{FE0F}' | '\u{E0100}'..='\u{E01EF}')
+}
+
+fn char_width(c: char, mode: WidthMode) -> usize {
+    if c == SOFT_HYPHEN {
+        return 0;                                                                   // Мягкие переносы невидимы, пока по ним не разорвана строка
+    }
+    if mode != WidthMode::Bytes && is_zero_width(c) {
+        return 0;                                                                   // Выводятся, но места не занимают
+    }
+    match mode {
+        WidthMode::Bytes => c.len_utf8(),
+        WidthMode::Chars => 1,
+        WidthMode::Columns if in_table(ZERO_WIDTH, c) => 0,
+        WidthMode::Columns if in_table(WIDE, c) => 2,
+        WidthMode::Columns => 1,
+        #[cfg(feature = "graphemes")]
+        WidthMode::Graphemes => 1,
+    }
+}
+
+pub(crate) const ESCAPE: char = '\u{1B}';
+
+fn escape_length(text: &str) -> usize {                                             // Длина управляющей последовательности ANSI (CSI или OSC) в начале текста
+    let bytes: &[u8] = text.as_bytes();
+    match bytes.get(1) {
+        Some(b'[') => {
+            bytes[2..].iter().position(|byte| (0x40..=0x7E).contains(byte)).map_or(bytes.len(), |index| index + 3)
+        },
+        Some(b']') => {
+            let mut index: usize = 2;
+            while index < bytes.len() {
+                match bytes[index] {
+                    0x07 => return index + 1,
+                    0x1B if bytes.get(index + 1) == Some(&b'\\') => return index + 2,
+                    _ => index += 1,
+                }
+            }
+            bytes.len()
+        },
+        Some(byte) if (0x40..=0x5F).contains(byte) => 2,
+        _ => 1,
+    }
+}
+
+fn segments<'t>(text: &'t str, options: &Options) -> impl Iterator<Item = (&'t str, bool)> + 't {   // Куски текста с признаком "управляющая последовательность"
+    let ansi: bool = options.ignore_ansi;
+    let mut rest: &str = text;
+    core::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let end: usize = match rest.find(ESCAPE).filter(|_| ansi) {
+            Some(0) => escape_length(rest),
+            Some(index) => index,
+            None => rest.len(),
+        };
+        let (segment, tail) = rest.split_at(end);
+        rest = tail;
+        Some((segment, ansi && segment.starts_with(ESCAPE)))
+    })
+}
+
+pub(crate) fn escape_ranges(text: &str, options: &Options) -> Vec<Range<usize>> {   // Байты управляющих последовательностей ANSI при Options::ignore_ansi
+    let mut offset: usize = 0;
+    segments(text, options)
+        .filter_map(|(segment, escape)| {
+            let range: Range<usize> = offset..offset + segment.len();
+            offset = range.end;
+            escape.then_some(range)
+        })
+        .collect()
+}
+
+fn measure_visible(text: &str, options: &Options) -> usize {
+    if text.chars().any(|c| is_hidden(c, options)) {
+        options.measure.width(&text.replace(|c| is_hidden(c, options), ""))
+    } else {
+        options.measure.width(text)
+    }
+}
+
+pub(crate) fn text_width(text: &str, options: &Options) -> usize {
+    segments(text, options)
+        .filter(|&(_, escape)| !escape)
+        .map(|(segment, _)| measure_visible(segment, options))
+        .fold(0, usize::saturating_add)                                             // Пользовательская мера может вернуть сколь угодно большую ширину
+}
+
+pub(crate) fn fit_prefix(text: &str, limit: usize, options: &Options) -> usize {       // Длина в байтах наибольшего начала текста не шире limit, но не короче одного кластера графем
+    let mut width: usize = 0;
+    let mut length: usize = 0;
+    for (segment, escape) in segments(text, options) {
+        let clusters: Vec<&str> = if escape { vec![segment] } else { Graphemes::new(segment).collect() };
+        for cluster in clusters {
+            width = width.saturating_add(if escape { 0 } else { measure_visible(cluster, options) });
+            if width > limit {
+                return if length == 0 { cluster.len() } else { length };
+            }
+            length += cluster.len();
+        }
+    }
+    text.len()
+}
@@ -0,0 +1,175 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+const HYPHEN_PENALTY: u128 = 25;                                                     // Штраф за перенос, чтобы оптимальный алгоритм не переносил без необходимости
+const GLUE_PENALTY: u128 = u64::MAX as u128;                                         // Разрыв склеенных слов - только если иначе никак
+
+pub(crate) trait Width: Copy + PartialOrd {                                         // Ширины, с которыми работают алгоритмы разбиения: символы (usize) или точки шрифта (f32)
+    const ZERO: Self;
+    fn plus(self, other: Self) -> Self;                                             // С насыщением у usize
+    fn minus(self, other: Self) -> Self;                                            // Не меньше нуля
+    fn squared(self) -> u128;                                                       // Квадрат недозаполнения строки в тех же единицах, что и HYPHEN_PENALTY
+    fn between(low: Self, high: Self) -> Option<Self>;                              // Середина промежутка для двоичного поиска, пока он не сошёлся
+    fn above(self) -> Self;                                                         // Где продолжать поиск после неудачной середины
+}
+
+impl Width for usize {
+    const ZERO: usize = 0;
+    fn plus(self, other: usize) -> usize {
+        self.saturating_add(other)
+    }
+    fn minus(self, other: usize) -> usize {
+        self.saturating_sub(other)
+    }
+    fn squared(self) -> u128 {
+        let slack: u128 = self as u128;                                             // Квадрат любой usize помещается в u128
+        slack * slack
+    }
+    fn between(low: usize, high: usize) -> Option<usize> {
+        (low < high).then(|| low + (high - low) / 2)
+    }
+    fn above(self) -> usize {
+        self + 1
+    }
+}
+
+impl Width for f32 {
+    const ZERO: f32 = 0.0;
+    fn plus(self, other: f32) -> f32 {
+        self + other
+    }
+    fn minus(self, other: f32) -> f32 {
+        (self - other).max(0.0)
+    }
+    fn squared(self) -> u128 {
+        let slack: f64 = f64::from(self);
+        (slack * slack).min(u64::MAX as f64) as u128
+    }
+    fn between(low: f32, high: f32) -> Option<f32> {
+        (high - low > high.abs().max(1.0) * f32::EPSILON * 4.0).then(|| low + (high - low) / 2.0)
+    }
+    fn above(self) -> f32 {
+        self
+    }
+}
+
+pub fn badness(slack: usize, gaps: usize, space_width: usize) -> u32 {             // Плохость строки, как в TeX: 100·r³, где r = slack / (gaps · space_width) - на сколько пробелов растянут каждый промежуток; не больше 10000. Только мера для отчёта: optimal ищет разбиение не по ней
+    const INFINITELY_BAD: f64 = 10_000.0;
+    if slack == 0 {
+        return 0;
+    }
+    let stretch: f64 = gaps as f64 * space_width.max(1) as f64;                    // Строку без промежутков не растянуть
+    let ratio: f64 = if stretch > 0.0 { slack as f64 / stretch } else { f64::INFINITY };
+    (100.0 * ratio * ratio * ratio).min(INFINITELY_BAD) as u32
+}
+
+fn widest<W: Width>(widths: impl Iterator<Item = W>) -> W {
+    widths.fold(W::ZERO, |widest, width| if width > widest { width } else { widest })
+}
+
+fn narrowest<W: Width>(first: W, second: W) -> W {
+    if second < first { second } else { first }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Item<W = usize> {
+    pub(crate) width: W,
+    pub(crate) whitespace: W,                                                         // Ширина промежутка после фрагмента, если строка продолжается
+    pub(crate) penalty: W,                                                            // Ширина знака переноса, если строка обрывается на фрагменте
+    pub(crate) glue: bool,                                                            // Строка не обрывается после фрагмента
+    pub(crate) hang: W,                                                               // Ширина знака в конце фрагмента (или знака переноса), выступающего за край строки
+}
+
+pub(crate) fn line_length<W: Width>(items: &[Item<W>]) -> W {
+    match items.split_last() {
+        Some((last, rest)) => rest.iter().map(|item| item.width.plus(item.whitespace)).fold(last.width.plus(last.penalty).minus(last.hang), W::plus),
+        None => W::ZERO,
+    }
+}
+
+pub(crate) fn greedy<W: Width>(items: &[Item<W>], line_width: &dyn Fn(usize) -> W) -> Vec<Range<usize>> {   // line_width - ширина строки по её номеру
+    let mut lines: Vec<Range<usize>> = Vec::new();
+    let mut start: usize = 0;
+
+    while start < items.len() {
+        let width: W = line_width(lines.len());
+        let mut end: usize = start + 1;                                             // Хотя бы один фрагмент попадает в строку, даже если не помещается
+        let mut length: W = W::ZERO;
+        for (index, item) in items.iter().enumerate().skip(start) {
+            if length.plus(item.width) > width.plus(item.hang) {
+                break;
+            }
+            if length.plus(item.width).plus(item.penalty).minus(item.hang) <= width && !item.glue {
+                end = index + 1;
+            }
+            length = length.plus(item.width).plus(item.whitespace);
+        }
+        lines.push(start..end);
+        start = end;
+    }
+    lines
+}
+
+pub(crate) fn balanced<W: Width>(items: &[Item<W>], line_width: &dyn Fn(usize) -> W) -> Vec<Range<usize>> {
+    let line_count: usize = greedy(items, line_width).len();
+    let (mut narrow, mut wide): (W, W) = (widest(items.iter().map(|item| item.width)), widest((0..line_count).map(line_width)));
+
+    while let Some(middle) = W::between(narrow, wide) {                             // Ищем наименьшую ширину, при которой число строк не растёт
+        if greedy(items, &|line| narrowest(line_width(line), middle)).len() <= line_count {
+            wide = middle;
+        } else {
+            narrow = middle.above();
+        }
+    }
+    greedy(items, &|line| narrowest(line_width(line), wide))
+}
+
+pub(crate) fn optimal<W: Width>(items: &[Item<W>], line_width: &dyn Fn(usize) -> W) -> Vec<Range<usize>> {
+    let item_count: usize = items.len();
+    let mut best: Vec<Option<u128>> = vec![None; item_count + 1];                   // best[j] - минимальная стоимость размещения первых j фрагментов: квадраты недобора строк и штрафы за перенос и склейку (не badness)
+    let mut line_start: Vec<usize> = vec![0; item_count + 1];
+    let mut line_number: Vec<usize> = vec![0; item_count + 1];                     // Номер строки, начинающейся после j фрагментов, на лучшем пути к j
+    let widest: W = widest((0..=item_count).map(line_width));                       // Строку длиннее самой широкой не поместить ни под каким номером
+    best[0] = Some(0);
+
+    for end in 1..=item_count {
+        for start in (0..end).rev() {
+            let width: W = line_width(line_number[start]);
+            let length: W = line_length(&items[start..end]);
+            if length > width && start + 1 < end {
+                if length.minus(items[end - 1].penalty) > widest {
+                    break;
+                }
+                continue;
+            }
+            let Some(cost) = best[start] else {
+                continue;
+            };
+            let badness: u128 =
+                if end == item_count {
+                    0                                                               // Последняя строка не растягивается и не штрафуется
+                } else {
+                    let penalty: u128 = if items[end - 1].penalty > W::ZERO { HYPHEN_PENALTY } else { 0 };
+                    let glue: u128 = if items[end - 1].glue { GLUE_PENALTY } else { 0 };
+                    width.minus(length).squared().saturating_add(penalty).saturating_add(glue)
+                };
+            let cost: u128 = cost.saturating_add(badness);
+            if best[end].is_none_or(|best| cost < best) {
+                best[end] = Some(cost);
+                line_start[end] = start;
+                line_number[end] = line_number[start] + 1;
+            }
+        }
+    }
+
+    let mut lines: Vec<Range<usize>> = Vec::new();
+    let mut end: usize = item_count;
+    while end > 0 {
+        let start: usize = line_start[end];
+        lines.push(start..end);
+        end = start;
+    }
+    lines.reverse();
+    lines
+}
@@ -0,0 +1,79 @@
+#![cfg(feature = "std")]
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_line-adjustment"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());                // Без `-` ввод никто не читает
+    child.wait_with_output().unwrap()
+}
+
+fn temp_file(name: &str, text: &str) -> PathBuf {                                  // Файл во временном каталоге, своём для каждого процесса теста
+    let path: PathBuf = std::env::temp_dir().join(format!("line-adjustment-{}-{}", std::process::id(), name));
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+#[test]
+fn width() {
+    let test_cases: [&[&str]; 4] = [
+        &["--width", "12"],
+        &["-w", "12"],
+        &["--width=12"],
+        &["--width", "12", "-"],
+    ];
+    for args in test_cases {
+        println!("args: {:?}", args);
+        let output: Output = run(args, "Lorem ipsum dolor sit amet");
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "Lorem  ipsum\ndolor    sit\namet        \n");
+    }
+    assert_eq!(String::from_utf8(run(&[], "Lorem").stdout).unwrap(), format!("{:<80}\n", "Lorem"));   // Ширина по умолчанию
+    let test_cases: [&[&str]; 3] = [
+        &["--width"],
+        &["--width", "wide"],
+        &["-w=12"],
+    ];
+    for args in test_cases {
+        println!("args: {:?}", args);
+        assert_eq!(run(args, "").status.code(), Some(if args == ["-w=12"] { 1 } else { 2 }));   // -w=12 - имя файла, которого нет
+    }
+}
+
+#[test]
+fn files() {
+    let first: PathBuf = temp_file("first", "Lorem ipsum dolor\n");
+    let empty: PathBuf = temp_file("empty", "");
+    let blank: PathBuf = temp_file("blank", "\n  \n");
+    let second: PathBuf = temp_file("second", "sit amet");
+    let path = |path: &PathBuf| path.to_str().unwrap().to_string();
+    let test_cases = [
+        (vec![path(&first), path(&second)], "Lorem  ipsum\ndolor       \n\nsit     amet\n"),
+        (vec![path(&first), path(&empty), path(&blank), path(&second)], "Lorem  ipsum\ndolor       \n\nsit     amet\n"),
+        (vec![path(&empty), path(&second), path(&empty)], "sit     amet\n"),
+        (vec![path(&first), String::from("-"), path(&second)], "Lorem  ipsum\ndolor       \n\nconsectetur \n\nsit     amet\n"),
+        (vec![path(&empty)], ""),
+    ];
+    for (files, expected) in &test_cases {
+        println!("files: {:?}", files);
+        let mut args: Vec<&str> = vec!["-w", "12"];
+        args.extend(files.iter().map(String::as_str));
+        let output: Output = run(&args, "consectetur");
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), *expected);
+    }
+    let output: Output = run(&["-w", "12", &path(&first), "missing-file"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "Lorem  ipsum\ndolor       \n");
+    for file in [first, empty, blank, second] {
+        std::fs::remove_file(file).unwrap();
+    }
+}